@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use iced::futures::SinkExt;
+use iced::futures::future::BoxFuture;
+use iced::futures::stream::BoxStream;
+
+use crate::nm;
+
+/// The NetworkManager operations `App` drives the state machine with. `App`
+/// only talks to NM through this, so it can be run against a fake in tests.
+pub trait Backend: Send + Sync {
+    fn list_wifi_devices(&self) -> BoxFuture<'static, Result<Vec<nm::WifiDevice>, String>>;
+
+    /// Ends with a `ScanProgress::Done`.
+    fn scan_networks(&self, device_path: String) -> BoxStream<'static, nm::ScanProgress>;
+
+    fn connect(
+        &self,
+        network: nm::Network,
+        password: String,
+        options: nm::ConnectOptions,
+        on_phase: Box<dyn FnMut(nm::ConnectPhase) + Send>,
+    ) -> BoxFuture<'static, Result<(), nm::ConnectError>>;
+
+    fn reconnect(
+        &self,
+        network: nm::Network,
+        on_phase: Box<dyn FnMut(nm::ConnectPhase) + Send>,
+    ) -> BoxFuture<'static, Result<(), nm::ConnectError>>;
+
+    /// Disconnects from `ssid`, or whatever the device is connected to when
+    /// `None`.
+    fn disconnect(
+        &self,
+        device_path: String,
+        ssid: Option<String>,
+    ) -> BoxFuture<'static, Result<(), String>>;
+
+    fn get_wifi_enabled(&self) -> BoxFuture<'static, Result<bool, String>>;
+
+    fn set_wifi_enabled(&self, enabled: bool) -> BoxFuture<'static, Result<bool, String>>;
+}
+
+/// The real backend, talking to NetworkManager over D-Bus.
+pub struct NetworkManager;
+
+impl Backend for NetworkManager {
+    fn list_wifi_devices(&self) -> BoxFuture<'static, Result<Vec<nm::WifiDevice>, String>> {
+        Box::pin(nm::list_wifi_devices())
+    }
+
+    fn scan_networks(&self, device_path: String) -> BoxStream<'static, nm::ScanProgress> {
+        Box::pin(iced::stream::channel(
+            10,
+            async move |mut output: iced::futures::channel::mpsc::Sender<nm::ScanProgress>| {
+                let mut partial = output.clone();
+                let result = nm::scan_networks(&device_path, |networks| {
+                    // Drop partial results if the UI lags behind, the final
+                    // result has them all
+                    let _ = partial.try_send(nm::ScanProgress::Partial(networks));
+                })
+                .await;
+                let _ = output.send(nm::ScanProgress::Done(result)).await;
+            },
+        ))
+    }
+
+    fn connect(
+        &self,
+        network: nm::Network,
+        password: String,
+        options: nm::ConnectOptions,
+        on_phase: Box<dyn FnMut(nm::ConnectPhase) + Send>,
+    ) -> BoxFuture<'static, Result<(), nm::ConnectError>> {
+        Box::pin(nm::connect(network, password, options, on_phase))
+    }
+
+    fn reconnect(
+        &self,
+        network: nm::Network,
+        on_phase: Box<dyn FnMut(nm::ConnectPhase) + Send>,
+    ) -> BoxFuture<'static, Result<(), nm::ConnectError>> {
+        Box::pin(nm::reconnect(network, on_phase))
+    }
+
+    fn disconnect(
+        &self,
+        device_path: String,
+        ssid: Option<String>,
+    ) -> BoxFuture<'static, Result<(), String>> {
+        Box::pin(async move { nm::disconnect(&device_path, ssid.as_deref()).await })
+    }
+
+    fn get_wifi_enabled(&self) -> BoxFuture<'static, Result<bool, String>> {
+        Box::pin(nm::get_wifi_enabled())
+    }
+
+    fn set_wifi_enabled(&self, enabled: bool) -> BoxFuture<'static, Result<bool, String>> {
+        Box::pin(nm::set_wifi_enabled(enabled))
+    }
+}
+
+/// A shared backend. Hashes by identity, so it can be part of a subscription
+/// key.
+#[derive(Clone)]
+pub struct Shared(pub Arc<dyn Backend>);
+
+impl std::ops::Deref for Shared {
+    type Target = dyn Backend;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl std::hash::Hash for Shared {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::ptr::hash(Arc::as_ptr(&self.0).cast::<()>(), state);
+    }
+}
+
+impl std::fmt::Debug for Shared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Backend")
+    }
+}
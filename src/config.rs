@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// User settings, read from `$XDG_CONFIG_HOME/netman/config`
+/// (`~/.config/netman/config` by default).
+///
+/// The file has one `key = value` setting per line. Empty lines and lines
+/// starting with `#` are ignored, as are unknown keys and invalid values, with
+/// a warning.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Rescan when the window gains focus.
+    pub refresh_on_focus: bool,
+    /// Security type preselected when joining a network by name, one of
+    /// `JOIN_SECURITY`.
+    pub join_security: &'static str,
+    /// Networks weaker than this (in percent) are hidden, except the connected
+    /// one.
+    pub min_strength: u8,
+    /// Keep the window above other windows.
+    pub always_on_top: bool,
+    /// Exit after connecting or disconnecting, like a popup menu.
+    pub menu_mode: bool,
+    /// Delete a new profile when its password is rejected, instead of keeping
+    /// it and asking for the password again.
+    pub auto_forget_on_auth_failure: bool,
+    /// Show desktop notifications when connecting, disconnecting or failing
+    /// to connect.
+    pub notifications: bool,
+    /// Show a tray icon with the connection status.
+    pub tray: bool,
+    /// Hide the window to the tray instead of exiting when it's closed.
+    pub close_to_tray: bool,
+    /// Interface to connect to an SSID with when several adapters can see it,
+    /// from `prefer_device.<SSID> = <interface>` lines.
+    pub preferred_devices: HashMap<String, String>,
+    /// Address the connectivity test connects to, `<ip>:<port>`.
+    pub probe_target: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            refresh_on_focus: true,
+            join_security: "WPA2",
+            min_strength: 0,
+            always_on_top: false,
+            menu_mode: false,
+            auto_forget_on_auth_failure: false,
+            notifications: false,
+            tray: false,
+            close_to_tray: false,
+            preferred_devices: HashMap::new(),
+            probe_target: crate::probe::DEFAULT_TARGET.to_string(),
+        }
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("netman").join("config"))
+}
+
+/// Read the config file. A missing file gives the defaults.
+pub fn load() -> Config {
+    let Some(path) = path() else {
+        return Config::default();
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => parse(&contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config::default(),
+        Err(e) => {
+            eprintln!("netman: can't read {}: {e}", path.display());
+            Config::default()
+        }
+    }
+}
+
+/// Set `key` to `value` in the config file, keeping the other lines.
+pub fn save(key: &str, value: &str) -> Result<(), String> {
+    update(key, Some(value))
+}
+
+/// Remove `key` from the config file, keeping the other lines.
+pub fn remove(key: &str) -> Result<(), String> {
+    update(key, None)
+}
+
+fn update(key: &str, value: Option<&str>) -> Result<(), String> {
+    let path = path().ok_or("Can't find the config directory, HOME isn't set")?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Failed to read {}: {e}", path.display())),
+    };
+
+    let setting = value.map(|value| format!("{key} = {value}"));
+    let mut found = false;
+    let mut lines: Vec<String> = contents
+        .lines()
+        .filter_map(|line| match line.rsplit_once('=') {
+            Some((k, _)) if k.trim() == key && !line.trim_start().starts_with('#') => {
+                found = true;
+                setting.clone()
+            }
+            _ => Some(line.to_string()),
+        })
+        .collect();
+    if !found && let Some(setting) = setting {
+        lines.push(setting);
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    std::fs::write(&path, lines.join("\n") + "\n")
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+fn parse(contents: &str) -> Config {
+    let mut config = Config::default();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Values never contain `=`, SSIDs in keys may
+        let Some((key, value)) = line.rsplit_once('=') else {
+            eprintln!("netman: config line {}: expected `key = value`", i + 1);
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let ok = match key {
+            "refresh_on_focus" => parse_bool(value).map(|v| config.refresh_on_focus = v),
+            "join_security" => crate::JOIN_SECURITY
+                .iter()
+                .find(|s| s.eq_ignore_ascii_case(value))
+                .map(|s| config.join_security = s),
+            "always_on_top" => parse_bool(value).map(|v| config.always_on_top = v),
+            "menu_mode" => parse_bool(value).map(|v| config.menu_mode = v),
+            "auto_forget_on_auth_failure" => {
+                parse_bool(value).map(|v| config.auto_forget_on_auth_failure = v)
+            }
+            "notifications" => parse_bool(value).map(|v| config.notifications = v),
+            "tray" => parse_bool(value).map(|v| config.tray = v),
+            "close_to_tray" => parse_bool(value).map(|v| config.close_to_tray = v),
+            "min_strength" => value
+                .parse::<u8>()
+                .ok()
+                .filter(|v| *v <= 100)
+                .map(|v| config.min_strength = v),
+            "probe_target" => value
+                .parse::<std::net::SocketAddr>()
+                .ok()
+                .map(|_| config.probe_target = value.to_string()),
+            key if key.starts_with("prefer_device.") => {
+                let ssid = &key["prefer_device.".len()..];
+                (!ssid.is_empty() && !value.is_empty()).then(|| {
+                    config
+                        .preferred_devices
+                        .insert(ssid.to_string(), value.to_string());
+                })
+            }
+            _ => {
+                eprintln!("netman: config line {}: unknown setting `{key}`", i + 1);
+                continue;
+            }
+        };
+        if ok.is_none() {
+            eprintln!("netman: config line {}: invalid value for `{key}`", i + 1);
+        }
+    }
+    config
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
@@ -1,16 +1,56 @@
+mod backend;
+mod config;
 mod nm;
+mod notify;
+mod probe;
+mod regdom;
+mod share;
+mod tray;
+
+use std::sync::Arc;
 
 use iced::futures::{SinkExt, StreamExt};
-use iced::widget::{button, column, container, pick_list, row, scrollable, text, text_input};
+use iced::widget::{
+    button, center, checkbox, column, container, mouse_area, opaque, pick_list, row, scrollable,
+    stack, text, text_input, tooltip,
+};
 use iced::{Element, Subscription, Task, Theme, event, keyboard, window};
 
+/// Exit code when there's no display to open the window on.
+const EXIT_NO_DISPLAY: i32 = 2;
+
+/// Whether a Wayland or X11 display is available.
+fn has_display() -> bool {
+    ["WAYLAND_DISPLAY", "WAYLAND_SOCKET", "DISPLAY"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|v| !v.is_empty()))
+}
+
 fn main() -> iced::Result {
-    iced::application(App::new, App::update, App::view)
+    // Without a display iced fails with an error that doesn't say why
+    if !has_display() {
+        eprintln!(
+            "netman: no display found (WAYLAND_DISPLAY and DISPLAY aren't set). \
+             netman needs a graphical session, use nmcli on headless machines."
+        );
+        std::process::exit(EXIT_NO_DISPLAY);
+    }
+
+    let mut config = config::load();
+    if std::env::args().skip(1).any(|arg| arg == "--menu") {
+        config.menu_mode = true;
+    }
+    let level = window_level(config.always_on_top);
+    let exit_on_close_request = !config.close_to_tray;
+    let boot = move || App::with_backend(config.clone(), Arc::new(backend::NetworkManager));
+    iced::application(boot, App::update, App::view)
         .title("netman")
         .subscription(App::subscription)
         .theme(Theme::Dark)
         .window(window::Settings {
             size: iced::Size::new(480.0, 500.0),
+            level,
+            exit_on_close_request,
             platform_specific: window::settings::PlatformSpecific {
                 application_id: "netman".to_string(),
                 ..Default::default()
@@ -20,51 +60,523 @@ fn main() -> iced::Result {
         .run()
 }
 
-enum App {
+fn window_level(always_on_top: bool) -> window::Level {
+    if always_on_top {
+        window::Level::AlwaysOnTop
+    } else {
+        window::Level::Normal
+    }
+}
+
+struct App {
+    config: config::Config,
+    backend: backend::Shared,
+    state: State,
+    scan_notice: ScanNotice,
+    probe: Probe,
+    strength: StrengthHistory,
+    /// Throughput of the selected device, while the diagnostics panel is open.
+    throughput: Option<Throughput>,
+    /// Open when sharing a network.
+    share: Option<ShareDialog>,
+    /// Regulatory domain, read when the diagnostics panel is opened. `None`
+    /// when it couldn't be read.
+    regdom: Option<String>,
+    /// Device path and capabilities of the adapter shown in the device info
+    /// panel, while it's open.
+    device_capabilities: Option<(String, Result<nm::WifiCapabilities, String>)>,
+    /// SSID of the network we connected to and when. Only known for
+    /// connections made from the app.
+    connected_since: Option<(String, std::time::Instant)>,
+    /// NM's connectivity check result.
+    connectivity: nm::Connectivity,
+    /// Saved WireGuard tunnels, shown as toggles above the network list.
+    wireguard: Vec<nm::WireGuard>,
+    /// List each band of a network separately, instead of one row per SSID.
+    show_bands: bool,
+    /// List the access points of each row, with their own strength.
+    show_all_aps: bool,
+    /// Name of the last message handled, other than periodic ones. Included
+    /// in the diagnostics copied from the error screen.
+    last_action: &'static str,
+    /// The tray icon is shown.
+    tray_active: bool,
+    /// What the tray icon was last updated to.
+    tray_status: Option<tray::Status>,
+}
+
+/// State of the user-triggered connectivity test.
+enum Probe {
+    Idle,
+    Running,
+    Done(Result<std::time::Duration, String>),
+}
+
+/// Number of samples kept for the signal strength and throughput graphs.
+const STRENGTH_SAMPLES: usize = 60;
+
+/// Recent signal strength samples of the connected access point.
+#[derive(Default)]
+struct StrengthHistory {
+    ap_path: Option<String>,
+    samples: std::collections::VecDeque<u8>,
+}
+
+impl StrengthHistory {
+    /// Record a sample. Samples of a different access point (or none) start a
+    /// new history.
+    fn push(&mut self, sample: Option<(String, u8)>) {
+        let Some((ap_path, strength)) = sample else {
+            *self = StrengthHistory::default();
+            return;
+        };
+        if self.ap_path.as_ref() != Some(&ap_path) {
+            self.ap_path = Some(ap_path);
+            self.samples.clear();
+        }
+        if self.samples.len() == STRENGTH_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(strength);
+    }
+
+    fn sparkline(&self) -> String {
+        sparkline(self.samples.iter().map(|s| u64::from(*s)), 100)
+    }
+}
+
+/// Render `values` as a row of block characters, scaled to `max`.
+fn sparkline(values: impl Iterator<Item = u64>, max: u64) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = max.max(1);
+    values
+        .map(|v| BARS[((v.min(max) * 7).div_ceil(max)) as usize])
+        .collect()
+}
+
+/// The dialog showing a saved network's QR code.
+struct ShareDialog {
+    ssid: Vec<u8>,
+    /// `None` while the password is read.
+    code: Option<Result<(nm::SharedNetwork, share::Code), String>>,
+}
+
+/// Recent receive and send rates of a device, in kbit/s, computed from
+/// the byte counters sampled once a second.
+#[derive(Default)]
+struct Throughput {
+    device_path: String,
+    last: Option<(std::time::Instant, u64, u64)>,
+    rx_kbps: std::collections::VecDeque<u64>,
+    tx_kbps: std::collections::VecDeque<u64>,
+    /// Set when sampling failed, sampling stops then.
+    error: Option<String>,
+}
+
+impl Throughput {
+    fn push(&mut self, device_path: String, rx: u64, tx: u64) {
+        if self.device_path != device_path {
+            *self = Throughput {
+                device_path,
+                ..Throughput::default()
+            };
+        }
+        let now = std::time::Instant::now();
+        if let Some((at, last_rx, last_tx)) = self.last
+            && rx >= last_rx
+            && tx >= last_tx
+        {
+            let ms = now.duration_since(at).as_millis().max(1) as u64;
+            // bytes/ms * 8 = kbit/s
+            for (samples, delta) in [
+                (&mut self.rx_kbps, rx - last_rx),
+                (&mut self.tx_kbps, tx - last_tx),
+            ] {
+                if samples.len() == STRENGTH_SAMPLES {
+                    samples.pop_front();
+                }
+                samples.push_back(delta * 8 / ms);
+            }
+        }
+        self.last = Some((now, rx, tx));
+    }
+}
+
+/// Format a rate given in kbit/s, e.g. "850 kbps", "12.3 Mbps".
+fn format_rate(kbps: u64) -> String {
+    if kbps < 1000 {
+        format!("{kbps} kbps")
+    } else {
+        format!("{:.1} Mbps", kbps as f64 / 1000.0)
+    }
+}
+
+/// Notice shown when NetworkManager refuses scan requests for lack of
+/// permission. It's shown once per session: after it's dismissed it stays
+/// hidden even if later scans are denied too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanNotice {
+    Hidden,
+    Shown,
+    Dismissed,
+}
+
+// There's only ever one of these, boxing `Loaded` would buy nothing
+#[allow(clippy::large_enum_variant)]
+enum State {
     Loading,
     Loaded {
         devices: Vec<nm::WifiDevice>,
         selected_device: usize,
         networks: Vec<nm::Network>,
         connecting_ssid: Option<String>,
+        /// Band of the row the password is entered on, when bands are listed
+        /// separately.
+        connecting_band: Option<&'static str>,
         password: String,
+        /// Why `password` can't be right, shown under the password field.
+        password_error: Option<String>,
+        /// Show the password instead of masking it.
+        password_visible: bool,
+        /// Options for the network in `connecting_ssid`.
+        options: nm::ConnectOptions,
+        /// Draft of `options` while the advanced settings editor is open.
+        advanced: Option<nm::ConnectOptions>,
         wifi_enabled: bool,
+        ip4: Option<nm::Ip4Info>,
+        /// Global IPv6 address of the device.
+        ip6: Option<String>,
+        /// Bit rate of the device in kb/s, 0 when not associated.
+        bitrate: u32,
+        /// `ipv4.method` of the active profile.
+        ip4_method: Option<String>,
+        /// Security the active profile connected with.
+        connected_security: Option<String>,
+        /// SSID of the active connection, shown until the first scan is done.
+        connected_ssid: Option<String>,
+        /// When `networks` was last populated by a scan.
+        scanned_at: Option<std::time::Instant>,
+        /// When NM last scanned, from its `LastScan`.
+        nm_scanned_at: Option<std::time::Instant>,
+        /// Short informational note shown above the list, e.g. when the
+        /// network we were entering a password for went out of range.
+        note: Option<String>,
+        /// The per-device actions menu, opened by right-clicking the header.
+        device_menu: bool,
+        /// Picking a network to switch to, forgetting the connected one.
+        switching: bool,
+        /// Row of the network list picked with the arrow keys.
+        highlighted: Option<usize>,
     },
     Connecting {
         devices: Vec<nm::WifiDevice>,
         selected_device: usize,
+        phase: nm::ConnectPhase,
+        request: ConnectRequest,
     },
     Disconnecting {
         devices: Vec<nm::WifiDevice>,
         selected_device: usize,
+        /// Network being disconnected from, when known.
+        ssid: Option<String>,
+    },
+    /// Form for joining a network by typing its SSID.
+    JoinOther {
+        devices: Vec<nm::WifiDevice>,
+        selected_device: usize,
+        ssid: String,
+        security: &'static str,
+        password: String,
+        error: Option<String>,
+    },
+    /// Editor for the IPv4 settings of a saved profile.
+    EditIp {
+        devices: Vec<nm::WifiDevice>,
+        selected_device: usize,
+        ssid: Vec<u8>,
+        /// `None` until the settings are loaded.
+        settings: Option<nm::Ip4Settings>,
+        /// Activate the profile after saving so the changes take effect.
+        reactivate: bool,
+        error: Option<String>,
+    },
+    /// Panel listing all active connections, of any type.
+    ActiveConnections {
+        devices: Vec<nm::WifiDevice>,
+        selected_device: usize,
+        /// `None` until the list is loaded.
+        connections: Option<Vec<nm::ActiveConnection>>,
+        error: Option<String>,
+    },
+    /// All saved networks, in range or not.
+    SavedNetworks {
+        devices: Vec<nm::WifiDevice>,
+        selected_device: usize,
+        /// `None` until the list is loaded.
+        networks: Option<Vec<nm::SavedNetwork>>,
+        error: Option<String>,
     },
     Error {
         message: String,
         devices: Option<Vec<nm::WifiDevice>>,
         selected_device: usize,
+        /// The action that failed, offered again with "Retry".
+        retry: Option<Retry>,
     },
 }
 
+/// A connect or disconnect that can be attempted again from the error screen.
+#[derive(Debug, Clone)]
+enum Retry {
+    Connect(Box<ConnectRequest>),
+    /// The SSID to disconnect from, see `Backend::disconnect`.
+    Disconnect(Option<String>),
+}
+
+/// Security types offered when joining a network by SSID. These are the
+/// `nm::Network::security` values `nm::connect` can build a profile for.
+const JOIN_SECURITY: [&str; 4] = ["WPA2", "WPA3", "WEP", "Open"];
+
 #[derive(Debug, Clone)]
 enum Message {
     DevicesLoaded(Result<Vec<nm::WifiDevice>, String>),
     DeviceSelected(nm::WifiDevice),
-    NetworksLoaded(Result<Vec<nm::Network>, String>, String),
+    NetworksLoaded(Result<nm::Scan, String>, String),
+    NetworksPartial(Vec<nm::Network>, String),
+    CurrentConnectionLoaded(String, Result<Option<String>, String>),
     NetworkChanged,
+    Ip4Changed,
+    Ip4Loaded(String, Option<nm::Ip4Info>),
     DevicesChanged,
+    PrimaryConnectionChanged,
+    DefaultRouteLoaded(Result<Option<String>, String>),
     Refresh,
     Back,
+    Reload,
+    /// Attempt the failed action on the error screen again.
+    Retry,
     Disconnect,
     Disconnected(Result<(), String>),
+    ConnectCancelled,
     Connect(String),
+    ConnectOnBand(String, &'static str),
+    HighlightUp,
+    HighlightDown,
+    /// Enter on the highlighted row, does what its main button does.
+    ActivateHighlighted,
+    ShowBands(bool),
+    ShowAllAps(bool),
+    AlwaysOnTop(bool),
+    /// Prefer the selected device for the SSID, or drop the preference.
+    PreferDevice(String, bool),
+    ConnectivityChanged(nm::Connectivity),
+    ConnectivityLoaded(Result<nm::Connectivity, String>),
+    WireGuardLoaded(Result<Vec<nm::WireGuard>, String>),
+    ToggleWireGuard(String, bool),
+    WireGuardToggled(Result<(), String>),
+    Reconnect(String),
+    JoinOther,
+    JoinSsidChanged(String),
+    JoinSecuritySelected(&'static str),
+    JoinPasswordChanged(String),
+    SubmitJoin,
     PasswordChanged(String),
+    TogglePasswordVisibility,
+    EditAdvanced,
+    KeyMgmtSelected(nm::KeyMgmt),
+    PinInterfaceToggled(bool),
+    IdentityChanged(String),
+    AnonymousIdentityChanged(String),
+    ApplyAdvanced,
+    CancelAdvanced,
     SubmitConnect,
     CancelConnect,
-    Connected(Result<(), String>),
+    ConnectProgress(nm::ConnectPhase),
+    Connected(Result<(), nm::ConnectError>),
     WifiEnabledChanged,
     WifiEnabledLoaded(Result<bool, String>),
     ToggleWifi(bool),
     WifiToggled(Result<bool, String>),
+    DismissScanNotice,
+    Tick,
+    Tray(tray::Event),
+    /// The window's close button was clicked, with `close_to_tray` set.
+    CloseRequested(window::Id),
+    TestConnectivity,
+    ConnectivityTested(Result<std::time::Duration, String>),
+    StrengthSampled(Result<Option<(String, u8)>, String>),
+    TargetStrengthSampled(Vec<u8>, u8),
+    /// An access point in the list reported a new strength.
+    StrengthUpdated(String, u8),
+    /// The device with the given path reported a new bit rate.
+    BitrateUpdated(String, u32),
+    ToggleDiagnostics,
+    ToggleDeviceMenu,
+    ToggleDeviceInfo,
+    DeviceInfoLoaded(String, Result<nm::WifiCapabilities, String>),
+    DisconnectDevice,
+    SetManaged(bool),
+    MakeDefaultRoute,
+    Reassociate,
+    DeviceActionDone(Result<(), String>),
+    CopyDiagnostics,
+    EditIp(Vec<u8>),
+    Ip4SettingsLoaded(Result<nm::Ip4Settings, String>),
+    Ip4ManualToggled(bool),
+    Ip4AddressChanged(String),
+    Ip4GatewayChanged(String),
+    Ip4DnsChanged(String),
+    Ip4ReactivateToggled(bool),
+    SaveIp4,
+    Ip4Saved(Result<(), String>),
+    StartSwitch,
+    CancelSwitch,
+    ForgetAndConnect(String),
+    Forgot(Result<(), String>, nm::Network),
+    /// Delete the saved profiles of the SSID.
+    Forget(Vec<u8>),
+    Forgotten(Result<(), String>),
+    /// Show a QR code guests can join the SSID with.
+    Share(Vec<u8>),
+    ShareLoaded(Vec<u8>, Result<nm::SharedNetwork, String>),
+    CloseShare,
+    /// Turn `connection.autoconnect` of the SSID's saved profile on or off.
+    SetAutoconnect(Vec<u8>, bool),
+    AutoconnectSet(Result<(), String>),
+    /// Mark the SSID's saved profile metered or not.
+    SetMetered(Vec<u8>, bool),
+    MeteredSet(Result<(), String>),
+    MeteredToggled(bool),
+    /// Connect to the SSID through the access point with the BSSID, and stay
+    /// on it. The band is that of the row, when bands are listed separately.
+    ConnectToAp(String, Option<&'static str>, String),
+    /// Stop pinning the SSID's saved profile to one access point.
+    Unpin(Vec<u8>),
+    Unpinned(Result<(), String>),
+    ShowActiveConnections,
+    ShowSavedNetworks,
+    SavedNetworksLoaded(Result<Vec<nm::SavedNetwork>, String>),
+    ActiveConnectionsLoaded(Result<Vec<nm::ActiveConnection>, String>),
+    Deactivate(String),
+    Deactivated(Result<(), String>),
+    TrafficSampled(String, Result<(u64, u64), String>),
+}
+
+impl Message {
+    /// The variant name, for the diagnostics. Payloads aren't included, they
+    /// may contain passwords.
+    fn name(&self) -> &'static str {
+        match self {
+            Message::DevicesLoaded(..) => "DevicesLoaded",
+            Message::DeviceSelected(..) => "DeviceSelected",
+            Message::NetworksLoaded(..) => "NetworksLoaded",
+            Message::NetworksPartial(..) => "NetworksPartial",
+            Message::CurrentConnectionLoaded(..) => "CurrentConnectionLoaded",
+            Message::NetworkChanged => "NetworkChanged",
+            Message::Ip4Changed => "Ip4Changed",
+            Message::Ip4Loaded(..) => "Ip4Loaded",
+            Message::DevicesChanged => "DevicesChanged",
+            Message::PrimaryConnectionChanged => "PrimaryConnectionChanged",
+            Message::DefaultRouteLoaded(..) => "DefaultRouteLoaded",
+            Message::Refresh => "Refresh",
+            Message::Back => "Back",
+            Message::Reload => "Reload",
+            Message::Retry => "Retry",
+            Message::Disconnect => "Disconnect",
+            Message::Disconnected(..) => "Disconnected",
+            Message::ConnectCancelled => "ConnectCancelled",
+            Message::Connect(..) => "Connect",
+            Message::ConnectOnBand(..) => "ConnectOnBand",
+            Message::HighlightUp => "HighlightUp",
+            Message::HighlightDown => "HighlightDown",
+            Message::ActivateHighlighted => "ActivateHighlighted",
+            Message::ShowBands(..) => "ShowBands",
+            Message::ShowAllAps(..) => "ShowAllAps",
+            Message::AlwaysOnTop(..) => "AlwaysOnTop",
+            Message::PreferDevice(..) => "PreferDevice",
+            Message::ConnectivityChanged(..) => "ConnectivityChanged",
+            Message::ConnectivityLoaded(..) => "ConnectivityLoaded",
+            Message::WireGuardLoaded(..) => "WireGuardLoaded",
+            Message::ToggleWireGuard(..) => "ToggleWireGuard",
+            Message::WireGuardToggled(..) => "WireGuardToggled",
+            Message::Reconnect(..) => "Reconnect",
+            Message::JoinOther => "JoinOther",
+            Message::JoinSsidChanged(..) => "JoinSsidChanged",
+            Message::JoinSecuritySelected(..) => "JoinSecuritySelected",
+            Message::JoinPasswordChanged(..) => "JoinPasswordChanged",
+            Message::SubmitJoin => "SubmitJoin",
+            Message::PasswordChanged(..) => "PasswordChanged",
+            Message::TogglePasswordVisibility => "TogglePasswordVisibility",
+            Message::EditAdvanced => "EditAdvanced",
+            Message::KeyMgmtSelected(..) => "KeyMgmtSelected",
+            Message::PinInterfaceToggled(..) => "PinInterfaceToggled",
+            Message::IdentityChanged(..) => "IdentityChanged",
+            Message::AnonymousIdentityChanged(..) => "AnonymousIdentityChanged",
+            Message::ApplyAdvanced => "ApplyAdvanced",
+            Message::CancelAdvanced => "CancelAdvanced",
+            Message::SubmitConnect => "SubmitConnect",
+            Message::CancelConnect => "CancelConnect",
+            Message::ConnectProgress(..) => "ConnectProgress",
+            Message::Connected(..) => "Connected",
+            Message::WifiEnabledChanged => "WifiEnabledChanged",
+            Message::WifiEnabledLoaded(..) => "WifiEnabledLoaded",
+            Message::ToggleWifi(..) => "ToggleWifi",
+            Message::WifiToggled(..) => "WifiToggled",
+            Message::DismissScanNotice => "DismissScanNotice",
+            Message::Tick => "Tick",
+            Message::Tray(..) => "Tray",
+            Message::CloseRequested(..) => "CloseRequested",
+            Message::TestConnectivity => "TestConnectivity",
+            Message::ConnectivityTested(..) => "ConnectivityTested",
+            Message::StrengthSampled(..) => "StrengthSampled",
+            Message::TargetStrengthSampled(..) => "TargetStrengthSampled",
+            Message::StrengthUpdated(..) => "StrengthUpdated",
+            Message::BitrateUpdated(..) => "BitrateUpdated",
+            Message::ToggleDiagnostics => "ToggleDiagnostics",
+            Message::ToggleDeviceMenu => "ToggleDeviceMenu",
+            Message::ToggleDeviceInfo => "ToggleDeviceInfo",
+            Message::DeviceInfoLoaded(..) => "DeviceInfoLoaded",
+            Message::DisconnectDevice => "DisconnectDevice",
+            Message::SetManaged(..) => "SetManaged",
+            Message::MakeDefaultRoute => "MakeDefaultRoute",
+            Message::Reassociate => "Reassociate",
+            Message::DeviceActionDone(..) => "DeviceActionDone",
+            Message::CopyDiagnostics => "CopyDiagnostics",
+            Message::EditIp(..) => "EditIp",
+            Message::Ip4SettingsLoaded(..) => "Ip4SettingsLoaded",
+            Message::Ip4ManualToggled(..) => "Ip4ManualToggled",
+            Message::Ip4AddressChanged(..) => "Ip4AddressChanged",
+            Message::Ip4GatewayChanged(..) => "Ip4GatewayChanged",
+            Message::Ip4DnsChanged(..) => "Ip4DnsChanged",
+            Message::Ip4ReactivateToggled(..) => "Ip4ReactivateToggled",
+            Message::SaveIp4 => "SaveIp4",
+            Message::Ip4Saved(..) => "Ip4Saved",
+            Message::StartSwitch => "StartSwitch",
+            Message::CancelSwitch => "CancelSwitch",
+            Message::ForgetAndConnect(..) => "ForgetAndConnect",
+            Message::Forgot(..) => "Forgot",
+            Message::Forget(..) => "Forget",
+            Message::Forgotten(..) => "Forgotten",
+            Message::Share(..) => "Share",
+            Message::ShareLoaded(..) => "ShareLoaded",
+            Message::CloseShare => "CloseShare",
+            Message::SetAutoconnect(..) => "SetAutoconnect",
+            Message::AutoconnectSet(..) => "AutoconnectSet",
+            Message::SetMetered(..) => "SetMetered",
+            Message::MeteredSet(..) => "MeteredSet",
+            Message::MeteredToggled(..) => "MeteredToggled",
+            Message::ConnectToAp(..) => "ConnectToAp",
+            Message::Unpin(..) => "Unpin",
+            Message::Unpinned(..) => "Unpinned",
+            Message::ShowActiveConnections => "ShowActiveConnections",
+            Message::ShowSavedNetworks => "ShowSavedNetworks",
+            Message::SavedNetworksLoaded(..) => "SavedNetworksLoaded",
+            Message::ActiveConnectionsLoaded(..) => "ActiveConnectionsLoaded",
+            Message::Deactivate(..) => "Deactivate",
+            Message::Deactivated(..) => "Deactivated",
+            Message::TrafficSampled(..) => "TrafficSampled",
+        }
+    }
 }
 
 #[allow(clippy::ptr_arg)]
@@ -73,9 +585,9 @@ fn nm_signals(device_path: &String) -> iced::futures::stream::BoxStream<'static,
     Box::pin(iced::stream::channel(
         10,
         async move |mut output: iced::futures::channel::mpsc::Sender<Message>| {
-            use nm::proxy::{NetworkManagerProxy, WirelessProxy};
+            use nm::proxy::{DeviceProxy, NetworkManagerProxy, WirelessProxy};
 
-            let Ok(conn) = zbus::Connection::system().await else {
+            let Ok(conn) = nm::bus().await else {
                 return;
             };
             let Ok(nm): Result<NetworkManagerProxy, _> = NetworkManagerProxy::new(&conn).await
@@ -97,15 +609,33 @@ fn nm_signals(device_path: &String) -> iced::futures::stream::BoxStream<'static,
             let Ok(ap_removed) = wireless.receive_access_point_removed().await else {
                 return;
             };
+            let Ok(device): Result<DeviceProxy, _> = DeviceProxy::builder(&conn)
+                .path(device_path.as_str())
+                .unwrap()
+                .build()
+                .await
+            else {
+                return;
+            };
             let active_changed = nm.receive_active_connections_changed().await;
+            let primary_changed = nm.receive_primary_connection_changed().await;
+            // A new IPv4 configuration, e.g. after a DHCP renewal
+            let ip4_changed = device.receive_ip4_config_changed().await;
 
-            let mut merged = iced::futures::stream::select(
+            let network_changed = iced::futures::stream::select(
                 iced::futures::stream::select(ap_added.map(|_| ()), ap_removed.map(|_| ())),
-                active_changed.map(|_| ()),
+                iced::futures::stream::select(
+                    active_changed.map(|_| ()),
+                    primary_changed.map(|_| ()),
+                ),
+            );
+            let mut merged = iced::futures::stream::select(
+                network_changed.map(|()| Message::NetworkChanged),
+                ip4_changed.map(|_| Message::Ip4Changed),
             );
 
-            while merged.next().await.is_some() {
-                let _ = output.send(Message::NetworkChanged).await;
+            while let Some(message) = merged.next().await {
+                let _ = output.send(message).await;
             }
         },
     ))
@@ -117,7 +647,7 @@ fn nm_device_signal_stream() -> iced::futures::stream::BoxStream<'static, Messag
         async move |mut output: iced::futures::channel::mpsc::Sender<Message>| {
             use nm::proxy::NetworkManagerProxy;
 
-            let Ok(conn) = zbus::Connection::system().await else {
+            let Ok(conn) = nm::bus().await else {
                 return;
             };
             let Ok(nm): Result<NetworkManagerProxy, _> = NetworkManagerProxy::new(&conn).await
@@ -131,13 +661,30 @@ fn nm_device_signal_stream() -> iced::futures::stream::BoxStream<'static, Messag
                 return;
             };
             let wifi_changed = nm.receive_wireless_enabled_changed().await;
+            let primary_changed = nm.receive_primary_connection_changed().await;
+            let connectivity_changed = nm
+                .receive_connectivity_changed()
+                .await
+                .filter_map(|change| async move {
+                    let state = change.get().await.ok()?;
+                    Some(Message::ConnectivityChanged(nm::Connectivity::from_state(
+                        state,
+                    )))
+                })
+                .boxed();
 
             let mut merged = iced::futures::stream::select(
                 iced::futures::stream::select(
                     dev_added.map(|_| Message::DevicesChanged),
                     dev_removed.map(|_| Message::DevicesChanged),
                 ),
-                wifi_changed.map(|_| Message::WifiEnabledChanged),
+                iced::futures::stream::select(
+                    iced::futures::stream::select(
+                        wifi_changed.map(|_| Message::WifiEnabledChanged),
+                        primary_changed.map(|_| Message::PrimaryConnectionChanged),
+                    ),
+                    connectivity_changed,
+                ),
             );
 
             while let Some(msg) = merged.next().await {
@@ -147,65 +694,544 @@ fn nm_device_signal_stream() -> iced::futures::stream::BoxStream<'static, Messag
     ))
 }
 
-impl App {
-    fn new() -> (Self, Task<Message>) {
-        (
-            App::Loading,
-            Task::perform(nm::list_wifi_devices(), Message::DevicesLoaded),
+/// Ticks once a second, for parts of the view that show elapsed time.
+fn ticks() -> impl iced::futures::Stream<Item = Message> {
+    async_io::Timer::interval(std::time::Duration::from_secs(1)).map(|_| Message::Tick)
+}
+
+/// Redraws for the loading skeleton's shimmer.
+fn skeleton_frames() -> impl iced::futures::Stream<Item = Message> {
+    async_io::Timer::interval(std::time::Duration::from_millis(100)).map(|_| Message::Tick)
+}
+
+/// Placeholder rows shown during the first scan, laid out like the network
+/// list so nothing jumps when the results arrive.
+fn skeleton_rows<'a>() -> iced::widget::Column<'a, Message> {
+    // Pulses between 10% and 25% opacity, once every 1.2s
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let phase = (millis % 1200) as f32 / 1200.0 * std::f32::consts::TAU;
+    let alpha = 0.175 + 0.075 * phase.sin();
+
+    let bar = move |width: f32, height: f32| {
+        container(iced::widget::Space::new().width(width).height(height)).style(
+            move |theme: &Theme| {
+                container::background(theme.palette().text.scale_alpha(alpha))
+                    .border(iced::border::rounded(4))
+            },
         )
+    };
+
+    let mut rows = column![];
+    for width in [180.0, 120.0, 150.0, 100.0, 140.0] {
+        rows = rows.push(
+            row![
+                column![bar(width, 14.0), bar(80.0, 10.0)].spacing(6),
+                iced::widget::space::horizontal(),
+                bar(70.0, 24.0),
+            ]
+            .align_y(iced::Alignment::Center)
+            .padding(6),
+        );
     }
+    rows
+}
 
-    fn subscription(&self) -> Subscription<Message> {
-        let kbd = event::listen_with(|event, _status, _window| match event {
-            event::Event::Keyboard(keyboard::Event::KeyPressed {
-                key: keyboard::Key::Named(keyboard::key::Named::Escape),
-                ..
-            }) => Some(Message::CancelConnect),
-            event::Event::Keyboard(keyboard::Event::KeyPressed {
-                key: keyboard::Key::Named(keyboard::key::Named::Enter),
-                ..
-            }) => Some(Message::Back),
-            _ => None,
+/// How many of the 4 bars a signal strength lights up.
+fn signal_bars(strength: u8) -> usize {
+    match strength {
+        0..=25 => 1,
+        26..=50 => 2,
+        51..=75 => 3,
+        _ => 4,
+    }
+}
+
+/// A 4-bar signal glyph, with a lock for secured networks. The percentage is
+/// in the tooltip.
+fn signal_icon<'a>(strength: u8, secured: bool) -> Element<'a, Message> {
+    let lit = signal_bars(strength);
+    let mut bars = row![].spacing(2).align_y(iced::Alignment::End);
+    for i in 0..4 {
+        let alpha = if i < lit { 1.0 } else { 0.25 };
+        bars = bars.push(
+            container(
+                iced::widget::Space::new()
+                    .width(3.0)
+                    .height(4.0 + 3.0 * i as f32),
+            )
+            .style(move |theme: &Theme| {
+                container::background(theme.palette().text.scale_alpha(alpha))
+                    .border(iced::border::rounded(1))
+            }),
+        );
+    }
+    let mut icon = row![bars].spacing(4).align_y(iced::Alignment::End);
+    if secured {
+        icon = icon.push(text("🔒").size(10));
+    }
+    tooltip(
+        icon,
+        container(text(format!("{strength}%")).size(12))
+            .padding([2, 6])
+            .style(container::rounded_box),
+        tooltip::Position::Top,
+    )
+    .into()
+}
+
+/// `dialog` centered over a dimmed `base`. Clicking outside of it sends
+/// `on_blur`.
+fn modal<'a>(
+    base: impl Into<Element<'a, Message>>,
+    dialog: impl Into<Element<'a, Message>>,
+    on_blur: Message,
+) -> Element<'a, Message> {
+    stack![
+        base.into(),
+        opaque(
+            mouse_area(center(opaque(dialog)).style(|_theme| {
+                container::Style {
+                    background: Some(
+                        iced::Color {
+                            a: 0.8,
+                            ..iced::Color::BLACK
+                        }
+                        .into(),
+                    ),
+                    ..container::Style::default()
+                }
+            }))
+            .on_press(on_blur)
+        )
+    ]
+    .into()
+}
+
+/// The QR code of a network being shared, with its password for typing it
+/// in by hand.
+fn share_dialog(share: &ShareDialog) -> Element<'_, Message> {
+    let mut col = column![text(format!("Join {}", String::from_utf8_lossy(&share.ssid))).size(18)]
+        .spacing(10)
+        .align_x(iced::Alignment::Center);
+    col = match &share.code {
+        None => col.push(text("Reading the password...").size(13)),
+        Some(Err(e)) => col.push(text(e).size(13).style(text::danger)),
+        Some(Ok((network, code))) => col.push(code.view(240.0)).push(
+            text(match &network.password {
+                Some(password) => format!("Password: {password}"),
+                None => "No password needed".to_string(),
+            })
+            .size(13),
+        ),
+    };
+    col = col.push(button("Close").on_press(Message::CloseShare));
+    container(col)
+        .padding(20)
+        .max_width(360)
+        .style(container::rounded_box)
+        .into()
+}
+
+/// NM's bit rate (kb/s) in Mbit/s, "not associated" for 0.
+fn format_bitrate(kbps: u32) -> String {
+    match kbps {
+        0 => "not associated".to_string(),
+        kbps => format!("{} Mbit/s", kbps / 1000),
+    }
+}
+
+/// Bands and capabilities of the selected adapter, from the device menu.
+fn device_info_panel<'a>(result: &Result<nm::WifiCapabilities, String>) -> Element<'a, Message> {
+    let caps = match result {
+        Ok(caps) => caps,
+        Err(e) => return text(e.clone()).size(13).style(text::danger).into(),
+    };
+
+    let mut info = column![].spacing(4).padding(6);
+    match caps.bands() {
+        Some(bands) => {
+            info = info.push(text(format!("Bands: {}", bands.join(", "))).size(13));
+            if caps.max_band() == Some("2.4 GHz") {
+                info = info.push(text("This adapter can't see 5 GHz networks").size(13));
+            }
+        }
+        None => info = info.push(text("Bands: not reported by the driver").size(13)),
+    }
+    let names = caps.names();
+    info = info
+        .push(
+            text(format!(
+                "Capabilities: {}",
+                if names.is_empty() {
+                    "none reported".to_string()
+                } else {
+                    names.join(", ")
+                }
+            ))
+            .size(13),
+        )
+        .push(text(format!("Bit rate: {}", format_bitrate(caps.bitrate))).size(13));
+    info.into()
+}
+
+/// Samples the signal strength of the access point the device is connected
+/// to every 2 seconds.
+#[allow(clippy::ptr_arg)]
+fn strength_samples(device_path: &String) -> iced::futures::stream::BoxStream<'static, Message> {
+    let device_path = device_path.clone();
+    Box::pin(
+        async_io::Timer::interval(std::time::Duration::from_secs(2)).then(move |_| {
+            let device_path = device_path.clone();
+            async move { Message::StrengthSampled(nm::active_ap_strength(&device_path).await) }
+        }),
+    )
+}
+
+/// Strength changes of the given access points. Changes are collected and
+/// sent once a second, the latest for each access point, so flapping access
+/// points don't flood the update loop.
+#[allow(clippy::ptr_arg)]
+fn strength_updates(ap_paths: &Vec<String>) -> iced::futures::stream::BoxStream<'static, Message> {
+    let ap_paths = ap_paths.clone();
+    Box::pin(iced::stream::channel(
+        10,
+        async move |mut output: iced::futures::channel::mpsc::Sender<Message>| {
+            use nm::proxy::AccessPointProxy;
+
+            let Ok(conn) = nm::bus().await else {
+                return;
+            };
+            let mut changes = Vec::new();
+            for path in ap_paths {
+                let Ok(builder) = AccessPointProxy::builder(&conn).path(path.clone()) else {
+                    continue;
+                };
+                let Ok(ap) = builder.build().await else {
+                    continue;
+                };
+                let strength_changed =
+                    ap.receive_strength_changed()
+                        .await
+                        .filter_map(move |change| {
+                            let path = path.clone();
+                            async move { Some((path, change.get().await.ok()?)) }
+                        });
+                changes.push(strength_changed.boxed());
+            }
+
+            let updates = iced::futures::stream::select_all(changes).map(Some);
+            let flushes =
+                async_io::Timer::interval(std::time::Duration::from_secs(1)).map(|_| None);
+            let mut events = iced::futures::stream::select(updates, flushes);
+            let mut pending = std::collections::HashMap::new();
+            while let Some(event) = events.next().await {
+                match event {
+                    Some((path, strength)) => {
+                        pending.insert(path, strength);
+                    }
+                    None => {
+                        for (path, strength) in pending.drain() {
+                            let _ = output.send(Message::StrengthUpdated(path, strength)).await;
+                        }
+                    }
+                }
+            }
+        },
+    ))
+}
+
+/// Bit rate changes of the device, from its PropertiesChanged signals.
+#[allow(clippy::ptr_arg)]
+fn bitrate_updates(device_path: &String) -> iced::futures::stream::BoxStream<'static, Message> {
+    let device_path = device_path.clone();
+    Box::pin(iced::stream::channel(
+        10,
+        async move |mut output: iced::futures::channel::mpsc::Sender<Message>| {
+            use nm::proxy::WirelessProxy;
+
+            let Ok(conn) = nm::bus().await else {
+                return;
+            };
+            let Ok(builder) = WirelessProxy::builder(&conn).path(device_path.clone()) else {
+                return;
+            };
+            let Ok(wireless) = builder.build().await else {
+                return;
+            };
+            let mut changes = wireless.receive_bitrate_changed().await;
+            while let Some(change) = changes.next().await {
+                if let Ok(bitrate) = change.get().await {
+                    let _ = output
+                        .send(Message::BitrateUpdated(device_path.clone(), bitrate))
+                        .await;
+                }
+            }
+        },
+    ))
+}
+
+/// Samples the signal strength of the network being joined every 2 seconds,
+/// so the password row shows it while moving around.
+fn target_strength_samples(
+    (device_path, ssid): &(String, Vec<u8>),
+) -> iced::futures::stream::BoxStream<'static, Message> {
+    let (device_path, ssid) = (device_path.clone(), ssid.clone());
+    Box::pin(
+        async_io::Timer::interval(std::time::Duration::from_secs(2)).filter_map(move |_| {
+            let (device_path, ssid) = (device_path.clone(), ssid.clone());
+            async move {
+                let strength = nm::strength_of(&device_path, &ssid).await?;
+                Some(Message::TargetStrengthSampled(ssid, strength))
+            }
+        }),
+    )
+}
+
+/// Samples the device's byte counters every second.
+#[allow(clippy::ptr_arg)]
+fn traffic_samples(device_path: &String) -> iced::futures::stream::BoxStream<'static, Message> {
+    let device_path = device_path.clone();
+    Box::pin(
+        async_io::Timer::interval(std::time::Duration::from_secs(1)).then(move |_| {
+            let device_path = device_path.clone();
+            async move {
+                let result = nm::device_traffic(&device_path).await;
+                Message::TrafficSampled(device_path, result)
+            }
+        }),
+    )
+}
+
+/// NM rejects scan requests this soon after its previous scan.
+const SCAN_COOLDOWN: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Format a duration coarsely, e.g. "8s", "3m", "2h".
+fn format_age(age: std::time::Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 60 {
+        format!("{secs}s")
+    } else if secs < 60 * 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}h", secs / (60 * 60))
+    }
+}
+
+/// Duration of a connection, e.g. "12m 30s".
+fn format_uptime(uptime: std::time::Duration) -> String {
+    let secs = uptime.as_secs();
+    let (hours, mins, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}h {mins}m")
+    } else if mins > 0 {
+        format!("{mins}m {secs}s")
+    } else {
+        format!("{secs}s")
+    }
+}
+
+/// A connection attempt. While the app is in `State::Connecting` the attempt
+/// runs as a subscription keyed on this, so leaving that state cancels it.
+#[derive(Debug, Clone, Hash)]
+struct ConnectRequest {
+    network: nm::Network,
+    password: String,
+    options: nm::ConnectOptions,
+    /// Only re-activate the saved profile, see `nm::reconnect`.
+    reconnect: bool,
+    backend: backend::Shared,
+}
+
+fn connect_stream(request: &ConnectRequest) -> iced::futures::stream::BoxStream<'static, Message> {
+    let request = request.clone();
+    Box::pin(iced::stream::channel(
+        10,
+        async move |mut output: iced::futures::channel::mpsc::Sender<Message>| {
+            let mut progress = output.clone();
+            let on_phase = move |phase| {
+                // Progress is best-effort, drop updates if the UI lags behind
+                let _ = progress.try_send(Message::ConnectProgress(phase));
+            };
+            let backend = request.backend;
+            let result = if request.reconnect {
+                backend.reconnect(request.network, Box::new(on_phase)).await
+            } else {
+                backend
+                    .connect(
+                        request.network,
+                        request.password,
+                        request.options,
+                        Box::new(on_phase),
+                    )
+                    .await
+            };
+            let _ = output.send(Message::Connected(result)).await;
+        },
+    ))
+}
+
+/// Start `request` with the settings from the config, on the device
+/// preferred for the SSID if that's not the selected one. The preferred
+/// device becomes the selected one, so the list shows the connection when
+/// it's up.
+fn connecting(
+    config: &config::Config,
+    devices: Vec<nm::WifiDevice>,
+    mut selected_device: usize,
+    mut request: ConnectRequest,
+) -> State {
+    let preferred = config
+        .preferred_devices
+        .get(&request.network.ssid)
+        .and_then(|interface| {
+            devices
+                .iter()
+                .position(|d| d.managed && &d.interface == interface)
         });
+    request.options.forget_on_auth_failure = config.auto_forget_on_auth_failure;
+    if let Some(i) = preferred
+        && i != selected_device
+    {
+        selected_device = i;
+        request.network.device_path = devices[i].path.clone();
+        // The access points were seen by the other device, NM picks one
+        request.network.ap_path = "/".to_string();
+        request.network.bssid = String::new();
+        request.network.access_points = Vec::new();
+    }
+    State::Connecting {
+        devices,
+        selected_device,
+        phase: nm::ConnectPhase::Starting,
+        request,
+    }
+}
 
-        let dev_signals = Subscription::run(nm_device_signal_stream);
+/// Scan a device, reporting partial results as they come in.
+fn scan_task(backend: &backend::Shared, device_path: String) -> Task<Message> {
+    Task::run(
+        backend.scan_networks(device_path.clone()),
+        move |progress| match progress {
+            nm::ScanProgress::Partial(networks) => {
+                Message::NetworksPartial(networks, device_path.clone())
+            }
+            nm::ScanProgress::Done(result) => Message::NetworksLoaded(result, device_path.clone()),
+        },
+    )
+}
+
+/// Look up the SSID a device is connected to, which is quicker than a scan.
+fn current_connection_task(device_path: String) -> Task<Message> {
+    Task::perform(
+        async move {
+            let result = nm::current_connection(&device_path).await;
+            (device_path, result)
+        },
+        |(device_path, result)| Message::CurrentConnectionLoaded(device_path, result),
+    )
+}
+
+/// The network with the given SSID, on the given band if there's one.
+fn find_network<'a>(
+    networks: &'a [nm::Network],
+    ssid: &str,
+    band: Option<&str>,
+) -> Option<&'a nm::Network> {
+    let on_band = |n: &&nm::Network| band.is_none_or(|band| n.band() == Some(band));
+    let mut candidates = networks.iter().filter(|n| n.ssid == ssid);
+    candidates
+        .clone()
+        .find(on_band)
+        .or_else(|| candidates.next())
+}
+
+/// One row per SSID, the connected or strongest of its bands, with the access
+/// point count of all bands.
+fn merge_bands(networks: &[nm::Network]) -> Vec<(&nm::Network, usize)> {
+    let mut rows: Vec<(&nm::Network, usize)> = Vec::new();
+    // `networks` is sorted connected first, then by strength, so the first
+    // entry of an SSID is the one to show
+    for network in networks {
+        match rows.iter_mut().find(|(n, _)| n.ssid == network.ssid) {
+            Some((_, ap_count)) => *ap_count += network.ap_count,
+            None => rows.push((network, network.ap_count)),
+        }
+    }
+    rows
+}
 
-        if let App::Loaded {
+impl State {
+    /// A freshly loaded state for the given devices, before any scan results arrive.
+    fn loaded(devices: Vec<nm::WifiDevice>, selected_device: usize) -> Self {
+        State::Loaded {
             devices,
             selected_device,
-            wifi_enabled,
-            ..
-        } = self
-            && *wifi_enabled
-        {
-            let device_path = devices[*selected_device].path.clone();
-            Subscription::batch([
-                kbd,
-                dev_signals,
-                Subscription::run_with(device_path, nm_signals),
-            ])
-        } else {
-            Subscription::batch([kbd, dev_signals])
+            networks: Vec::new(),
+            connecting_ssid: None,
+            connecting_band: None,
+            password: String::new(),
+            options: nm::ConnectOptions::default(),
+            advanced: None,
+            wifi_enabled: true,
+            ip4: None,
+            ip6: None,
+            bitrate: 0,
+            ip4_method: None,
+            connected_security: None,
+            connected_ssid: None,
+            scanned_at: None,
+            nm_scanned_at: None,
+            note: None,
+            device_menu: false,
+            switching: false,
+            highlighted: None,
+            password_error: None,
+            password_visible: false,
         }
     }
 
     /// Helper: get devices and selected index from current state (for state transitions).
     fn device_info(&self) -> Option<(Vec<nm::WifiDevice>, usize)> {
         match self {
-            App::Loaded {
+            State::Loaded {
+                devices,
+                selected_device,
+                ..
+            }
+            | State::Connecting {
+                devices,
+                selected_device,
+                ..
+            }
+            | State::Disconnecting {
                 devices,
                 selected_device,
                 ..
             }
-            | App::Connecting {
+            | State::JoinOther {
                 devices,
                 selected_device,
+                ..
+            }
+            | State::ActiveConnections {
+                devices,
+                selected_device,
+                ..
+            }
+            | State::SavedNetworks {
+                devices,
+                selected_device,
+                ..
             }
-            | App::Disconnecting {
+            | State::EditIp {
                 devices,
                 selected_device,
+                ..
             } => Some((devices.clone(), *selected_device)),
-            App::Error {
+            State::Error {
                 devices: Some(devices),
                 selected_device,
                 ..
@@ -213,180 +1239,824 @@ impl App {
             _ => None,
         }
     }
+}
 
-    /// Transition to error state, preserving device info if available.
-    fn goto_error(&mut self, e: String) {
-        let info = self.device_info();
-        *self = App::Error {
-            message: e,
-            devices: info.as_ref().map(|(d, _)| d.clone()),
-            selected_device: info.map(|(_, s)| s).unwrap_or(0),
-        };
-    }
-
-    /// Helper: scan networks for the currently selected device.
-    fn scan_selected(&self, devices: &[nm::WifiDevice], selected: usize) -> Task<Message> {
-        let path = devices[selected].path.clone();
-        Task::perform(
-            async move {
-                let result = nm::scan_networks(&path).await;
-                (result, path)
+impl App {
+    fn with_backend(
+        config: config::Config,
+        backend: Arc<dyn backend::Backend>,
+    ) -> (Self, Task<Message>) {
+        let backend = backend::Shared(backend);
+        let task = Task::perform(backend.list_wifi_devices(), Message::DevicesLoaded);
+        (
+            App {
+                config,
+                backend,
+                state: State::Loading,
+                scan_notice: ScanNotice::Hidden,
+                probe: Probe::Idle,
+                strength: StrengthHistory::default(),
+                throughput: None,
+                share: None,
+                regdom: None,
+                device_capabilities: None,
+                connected_since: None,
+                connectivity: nm::Connectivity::Unknown,
+                wireguard: Vec::new(),
+                show_bands: false,
+                show_all_aps: false,
+                last_action: "",
+                tray_active: false,
+                tray_status: None,
             },
-            |(result, path)| Message::NetworksLoaded(result, path),
+            task,
         )
     }
 
-    fn update(&mut self, message: Message) -> Task<Message> {
-        match message {
-            Message::DevicesLoaded(result) => match result {
-                Ok(devices) => {
-                    // Preserve previous selection if the device still exists
-                    let prev_path = self.device_info().map(|(d, s)| d[s].path.clone());
-                    let selected = prev_path
-                        .and_then(|p| devices.iter().position(|d| d.path == p))
-                        .unwrap_or(0);
-                    let task = self.scan_selected(&devices, selected);
-                    *self = App::Loaded {
-                        devices,
-                        selected_device: selected,
-                        networks: Vec::new(),
-                        connecting_ssid: None,
+    /// Key map:
+    ///
+    /// - Escape: closes the password entry or the join form, cancels a
+    ///   connection attempt, leaves the error screen. Quits from the network
+    ///   list.
+    /// - Enter: submits the focused text input. On the error screen, goes back
+    ///   to the network list. On the network list, activates the highlighted
+    ///   row.
+    /// - Up/Down: move the highlight through the network list.
+    fn subscription(&self) -> Subscription<Message> {
+        let kbd = event::listen_with(|event, _status, _window| match event {
+            event::Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Named(keyboard::key::Named::Escape),
+                ..
+            }) => Some(Message::CancelConnect),
+            _ => None,
+        });
+
+        let dev_signals = Subscription::run(nm_device_signal_stream);
+
+        let focus = if self.config.refresh_on_focus {
+            event::listen_with(|event, _status, _window| match event {
+                event::Event::Window(window::Event::Focused) => Some(Message::NetworkChanged),
+                _ => None,
+            })
+        } else {
+            Subscription::none()
+        };
+
+        let tray = if self.config.tray {
+            Subscription::run(tray::run).map(Message::Tray)
+        } else {
+            Subscription::none()
+        };
+        let close = if self.config.close_to_tray {
+            window::close_requests().map(Message::CloseRequested)
+        } else {
+            Subscription::none()
+        };
+
+        let subscription = match &self.state {
+            State::Loaded {
+                devices,
+                selected_device,
+                wifi_enabled: true,
+                scanned_at,
+                networks,
+                connecting_ssid,
+                ..
+            } => {
+                let device_path = devices[*selected_device].path.clone();
+                // Only keys text inputs don't use
+                let nav = event::listen_with(|event, status, _window| match event {
+                    event::Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: keyboard::Key::Named(key),
+                        ..
+                    }) if status == event::Status::Ignored => match key {
+                        keyboard::key::Named::ArrowUp => Some(Message::HighlightUp),
+                        keyboard::key::Named::ArrowDown => Some(Message::HighlightDown),
+                        keyboard::key::Named::Enter => Some(Message::ActivateHighlighted),
+                        _ => None,
+                    },
+                    _ => None,
+                });
+                let mut subscriptions = vec![
+                    kbd,
+                    nav,
+                    focus,
+                    dev_signals,
+                    Subscription::run_with(device_path.clone(), nm_signals),
+                ];
+                if scanned_at.is_some() {
+                    subscriptions.push(Subscription::run(ticks));
+                } else if networks.is_empty() {
+                    subscriptions.push(Subscription::run(skeleton_frames));
+                }
+                if networks.iter().any(|n| n.is_connected) {
+                    subscriptions.push(Subscription::run_with(
+                        device_path.clone(),
+                        strength_samples,
+                    ));
+                    subscriptions
+                        .push(Subscription::run_with(device_path.clone(), bitrate_updates));
+                }
+                let ap_paths: Vec<String> = networks
+                    .iter()
+                    .map(|n| n.ap_path.clone())
+                    .filter(|path| path != "/")
+                    .collect();
+                if !ap_paths.is_empty() {
+                    subscriptions.push(Subscription::run_with(ap_paths, strength_updates));
+                }
+                if let Some(network) = connecting_ssid
+                    .as_ref()
+                    .and_then(|ssid| networks.iter().find(|n| n.ssid == *ssid))
+                {
+                    subscriptions.push(Subscription::run_with(
+                        (device_path.clone(), network.ssid_bytes.clone()),
+                        target_strength_samples,
+                    ));
+                }
+                if self
+                    .throughput
+                    .as_ref()
+                    .is_some_and(|throughput| throughput.error.is_none())
+                {
+                    subscriptions.push(Subscription::run_with(device_path, traffic_samples));
+                }
+                Subscription::batch(subscriptions)
+            }
+            State::Connecting { request, .. } => Subscription::batch([
+                kbd,
+                dev_signals,
+                Subscription::run_with(request.clone(), connect_stream),
+            ]),
+            State::Error { .. } => {
+                let enter = event::listen_with(|event, status, _window| match event {
+                    event::Event::Keyboard(keyboard::Event::KeyPressed {
+                        key: keyboard::Key::Named(keyboard::key::Named::Enter),
+                        ..
+                    }) if status == event::Status::Ignored => Some(Message::Back),
+                    _ => None,
+                });
+                Subscription::batch([kbd, enter, dev_signals])
+            }
+            State::Loading => {
+                Subscription::batch([kbd, dev_signals, Subscription::run(skeleton_frames)])
+            }
+            _ => Subscription::batch([kbd, dev_signals]),
+        };
+        Subscription::batch([subscription, tray, close])
+    }
+
+    /// A desktop notification, when they're enabled.
+    fn notify(&self, summary: &str, body: String) -> Task<Message> {
+        if !self.config.notifications {
+            return Task::none();
+        }
+        Task::future(notify::send(summary.to_string(), body)).discard()
+    }
+
+    /// Transition to error state, preserving device info if available.
+    fn goto_error(&mut self, e: String) {
+        let info = self.state.device_info();
+        self.state = State::Error {
+            message: e,
+            devices: info.as_ref().map(|(d, _)| d.clone()),
+            selected_device: info.map(|(_, s)| s).unwrap_or(0),
+            retry: None,
+        };
+    }
+
+    /// `goto_error` for a failed action, offering to retry it.
+    fn goto_error_retrying(&mut self, e: String, action: Retry) {
+        self.goto_error(e);
+        if let State::Error { retry, .. } = &mut self.state {
+            *retry = Some(action);
+        }
+    }
+
+    /// Helper: scan networks for the currently selected device.
+    fn scan_selected(&self, devices: &[nm::WifiDevice], selected: usize) -> Task<Message> {
+        // NM doesn't scan with unmanaged devices
+        if !devices[selected].managed {
+            return Task::none();
+        }
+        scan_task(&self.backend, devices[selected].path.clone())
+    }
+
+    /// The network list rows with the view settings applied, and the access
+    /// point count to show for each. The connected network is always the
+    /// first row, whatever the filters, so it can always be disconnected.
+    fn list_rows<'a>(&self, networks: &'a [nm::Network]) -> Vec<(&'a nm::Network, usize)> {
+        let mut rows = if self.show_bands {
+            networks.iter().map(|n| (n, n.ap_count)).collect()
+        } else {
+            merge_bands(networks)
+        };
+        rows.retain(|(n, _)| n.strength >= self.config.min_strength);
+
+        if let Some(connected) = networks.iter().find(|n| n.is_connected) {
+            let ap_count = if self.show_bands {
+                connected.ap_count
+            } else {
+                networks
+                    .iter()
+                    .filter(|n| n.ssid == connected.ssid)
+                    .map(|n| n.ap_count)
+                    .sum()
+            };
+            // Merged rows are one per SSID, replace its row whichever band
+            // it shows
+            rows.retain(|(n, _)| {
+                !std::ptr::eq(*n, connected) && (self.show_bands || n.ssid != connected.ssid)
+            });
+            rows.insert(0, (connected, ap_count));
+        }
+        rows
+    }
+
+    /// What a row's main button does.
+    fn row_action(network: &nm::Network, show_bands: bool, switching: bool) -> Message {
+        match (network.is_connected, switching) {
+            (true, true) => Message::CancelSwitch,
+            (true, false) => Message::Disconnect,
+            (false, true) => Message::ForgetAndConnect(network.ssid.clone()),
+            (false, false) if network.is_saved => Message::Reconnect(network.ssid.clone()),
+            (false, false) => match network.band() {
+                Some(band) if show_bands => Message::ConnectOnBand(network.ssid.clone(), band),
+                _ => Message::Connect(network.ssid.clone()),
+            },
+        }
+    }
+
+    /// Back to the network list after connecting or disconnecting.
+    fn connection_changed(&mut self) -> Task<Message> {
+        // The previous connectivity result and signal history no longer apply
+        self.probe = Probe::Idle;
+        self.strength = StrengthHistory::default();
+        if let Some((devices, selected)) = self.state.device_info() {
+            let task = self.scan_selected(&devices, selected);
+            self.state = State::loaded(devices, selected);
+            return task;
+        }
+        Task::none()
+    }
+
+    /// Whether WiFi is turned off, in which case connecting can only fail.
+    /// Leaves a note saying so.
+    fn blocked_by_wifi_off(&mut self) -> bool {
+        if let State::Loaded {
+            wifi_enabled: false,
+            note,
+            ..
+        } = &mut self.state
+        {
+            *note = Some("Turn on WiFi first".to_string());
+            return true;
+        }
+        false
+    }
+
+    /// Connect to a network from the list, or ask for its password first.
+    /// With `bssid`, the profile is pinned to that access point.
+    fn start_connect(
+        &mut self,
+        ssid: String,
+        band: Option<&'static str>,
+        bssid: Option<String>,
+    ) -> Task<Message> {
+        if self.blocked_by_wifi_off() {
+            return Task::none();
+        }
+        if let State::Loaded {
+            devices,
+            selected_device,
+            networks,
+            connecting_ssid,
+            connecting_band,
+            password,
+            password_error,
+            password_visible,
+            options,
+            advanced,
+            note,
+            ..
+        } = &mut self.state
+        {
+            *note = None;
+            // Open or saved networks: connect immediately (no password needed)
+            if let Some(net) = find_network(networks, &ssid, band)
+                && net.connects_instantly()
+            {
+                let net = net.clone();
+                let devs = devices.clone();
+                let sel = *selected_device;
+                self.state = connecting(
+                    &self.config,
+                    devs,
+                    sel,
+                    ConnectRequest {
+                        network: net,
                         password: String::new(),
-                        wifi_enabled: true,
-                    };
+                        options: nm::ConnectOptions {
+                            bssid,
+                            ..Default::default()
+                        },
+                        reconnect: false,
+                        backend: self.backend.clone(),
+                    },
+                );
+                return Task::none();
+            }
+            *options = nm::ConnectOptions {
+                bssid,
+                ..Default::default()
+            };
+            let enterprise = find_network(networks, &ssid, band)
+                .is_some_and(|n| options.key_mgmt_for(n) == nm::KeyMgmt::WpaEap);
+            *connecting_ssid = Some(ssid);
+            *connecting_band = band;
+            *password = String::new();
+            *password_error = None;
+            *password_visible = false;
+            *advanced = None;
+            return iced::widget::operation::focus(if enterprise {
+                "identity-input"
+            } else {
+                "password-input"
+            });
+        }
+        Task::none()
+    }
+
+    fn update(&mut self, message: Message) -> Task<Message> {
+        let task = self.update_state(message);
+        self.sync_connected_since();
+        Task::batch([task, self.sync_tray()])
+    }
+
+    /// Forget when the connection started once a scan shows it's gone.
+    fn sync_connected_since(&mut self) {
+        let State::Loaded {
+            networks,
+            scanned_at: Some(_),
+            ..
+        } = &self.state
+        else {
+            return;
+        };
+        let connected = networks.iter().find(|n| n.is_connected).map(|n| &n.ssid);
+        if self
+            .connected_since
+            .as_ref()
+            .is_some_and(|(ssid, _)| Some(ssid) != connected)
+        {
+            self.connected_since = None;
+        }
+    }
+
+    /// Update the tray icon when the status it shows changed.
+    fn sync_tray(&mut self) -> Task<Message> {
+        if !self.tray_active {
+            return Task::none();
+        }
+        let status = match &self.state {
+            State::Loaded {
+                wifi_enabled: false,
+                ..
+            } => tray::Status::wifi_off(),
+            State::Loaded { networks, .. } => match networks.iter().find(|n| n.is_connected) {
+                Some(network) => tray::Status::connected(&network.ssid, network.strength),
+                None => tray::Status::disconnected(),
+            },
+            State::Connecting { request, .. } => tray::Status::connecting(&request.network.ssid),
+            // Keep showing the last status through the other screens
+            _ => return Task::none(),
+        };
+        if self.tray_status.as_ref() == Some(&status) {
+            return Task::none();
+        }
+        self.tray_status = Some(status.clone());
+        Task::future(tray::update(status)).discard()
+    }
+
+    fn update_state(&mut self, message: Message) -> Task<Message> {
+        if !matches!(
+            message,
+            Message::Tick
+                | Message::NetworksPartial(..)
+                | Message::StrengthSampled(_)
+                | Message::TargetStrengthSampled(..)
+                | Message::StrengthUpdated(..)
+                | Message::BitrateUpdated(..)
+                | Message::TrafficSampled(..)
+                | Message::CopyDiagnostics
+        ) {
+            self.last_action = message.name();
+        }
+
+        match message {
+            Message::DevicesLoaded(result) => match result {
+                Ok(devices) => {
+                    // Preserve previous selection if the device still exists
+                    let prev_path = self.state.device_info().map(|(d, s)| d[s].path.clone());
+                    let selected = prev_path
+                        .and_then(|p| devices.iter().position(|d| d.path == p))
+                        .unwrap_or(0);
+                    let task = self.scan_selected(&devices, selected);
+                    let current = current_connection_task(devices[selected].path.clone());
+                    self.state = State::loaded(devices, selected);
                     Task::batch([
                         task,
-                        Task::perform(nm::get_wifi_enabled(), Message::WifiEnabledLoaded),
+                        current,
+                        Task::perform(nm::list_wireguard(), Message::WireGuardLoaded),
+                        Task::perform(nm::connectivity(), Message::ConnectivityLoaded),
+                        Task::perform(self.backend.get_wifi_enabled(), Message::WifiEnabledLoaded),
                     ])
                 }
                 Err(e) => {
-                    *self = App::Error {
+                    self.state = State::Error {
                         message: e,
                         devices: None,
                         selected_device: 0,
+                        retry: None,
                     };
                     Task::none()
                 }
             },
             Message::DeviceSelected(device) => {
-                if let App::Loaded {
+                if let State::Loaded {
                     devices,
                     selected_device,
                     networks,
                     connecting_ssid,
                     password,
+                    password_visible,
+                    connected_ssid,
+                    scanned_at,
                     ..
-                } = self
+                } = &mut self.state
                     && let Some(idx) = devices.iter().position(|d| d == &device)
                 {
                     *selected_device = idx;
                     *networks = Vec::new();
+                    *connected_ssid = None;
+                    *scanned_at = None;
                     *connecting_ssid = None;
                     *password = String::new();
+                    *password_visible = false;
                     let path = devices[idx].path.clone();
-                    return Task::perform(
-                        async move {
-                            let result = nm::scan_networks(&path).await;
-                            (result, path)
-                        },
-                        |(result, path)| Message::NetworksLoaded(result, path),
-                    );
+                    return Task::batch([
+                        scan_task(&self.backend, path.clone()),
+                        current_connection_task(path),
+                    ]);
+                }
+                Task::none()
+            }
+            Message::CurrentConnectionLoaded(for_device, result) => {
+                // Only a placeholder until the scan is done, not worth an
+                // error screen if it fails
+                if let Ok(ssid) = result
+                    && let State::Loaded {
+                        devices,
+                        selected_device,
+                        connected_ssid,
+                        ..
+                    } = &mut self.state
+                    && devices[*selected_device].path == for_device
+                {
+                    *connected_ssid = ssid;
+                }
+                Task::none()
+            }
+            Message::NetworksPartial(partial, for_device) => {
+                // Only fill an empty list, replacing a complete list with a
+                // partial one would make rows disappear and come back
+                if let State::Loaded {
+                    devices,
+                    selected_device,
+                    networks,
+                    scanned_at: None,
+                    ..
+                } = &mut self.state
+                    && devices[*selected_device].path == for_device
+                    && networks.len() <= partial.len()
+                {
+                    *networks = partial;
                 }
                 Task::none()
             }
             Message::NetworksLoaded(result, for_device) => {
                 match result {
-                    Ok(nets) => {
-                        if let App::Loaded {
+                    Ok(scan) => {
+                        if scan.scan_denied && self.scan_notice == ScanNotice::Hidden {
+                            self.scan_notice = ScanNotice::Shown;
+                        }
+                        // Don't interrupt connection attempts and panels, only
+                        // recover from errors
+                        if let State::Error { .. } = self.state
+                            && let Some((devices, selected_device)) = self.state.device_info()
+                            && devices[selected_device].path == for_device
+                        {
+                            self.state = State::loaded(devices, selected_device);
+                        }
+                        if let State::Loaded {
                             devices,
                             selected_device,
                             networks,
+                            connecting_ssid,
+                            password,
+                            password_visible,
+                            advanced,
+                            ip4,
+                            ip6,
+                            bitrate,
+                            ip4_method,
+                            connected_security,
+                            scanned_at,
+                            nm_scanned_at,
+                            note,
                             ..
-                        } = self
+                        } = &mut self.state
+                            && devices[*selected_device].path == for_device
                         {
-                            if devices[*selected_device].path == for_device {
-                                *networks = nets;
+                            *networks = scan.networks;
+                            *ip4 = scan.ip4;
+                            *ip6 = scan.ip6;
+                            *bitrate = scan.bitrate;
+                            *ip4_method = scan.ip4_method;
+                            *connected_security = scan.connected_security;
+                            *scanned_at = Some(std::time::Instant::now());
+                            *nm_scanned_at = scan
+                                .last_scan_age
+                                .and_then(|age| std::time::Instant::now().checked_sub(age));
+
+                            // Keep the password entry open across rescans, unless the
+                            // network is gone
+                            if let Some(ssid) = connecting_ssid
+                                && !networks.iter().any(|n| n.ssid == *ssid)
+                            {
+                                *note = Some(format!("{ssid} is no longer in range"));
+                                *connecting_ssid = None;
+                                *password = String::new();
+                                *password_visible = false;
+                                *advanced = None;
+                            } else if connecting_ssid.is_some() && advanced.is_none() {
+                                // The password row may have moved in the new
+                                // list, which resets the input's state. Keep
+                                // it focused so typing isn't interrupted.
+                                return iced::widget::operation::focus("password-input");
                             }
-                        } else if let Some((devices, selected_device)) = self.device_info()
-                            && devices[selected_device].path == for_device
-                        {
-                            *self = App::Loaded {
-                                devices,
-                                selected_device,
-                                networks: nets,
-                                connecting_ssid: None,
-                                password: String::new(),
-                                wifi_enabled: true,
-                            };
                         }
                     }
                     Err(e) => self.goto_error(e),
                 }
                 Task::none()
             }
-            Message::NetworkChanged => {
-                if let App::Loaded {
+            Message::Ip4Changed => {
+                if let State::Loaded {
                     devices,
                     selected_device,
                     ..
-                } = self
+                } = &self.state
                 {
                     let path = devices[*selected_device].path.clone();
                     return Task::perform(
                         async move {
-                            let result = nm::scan_networks(&path).await;
-                            (result, path)
+                            let ip4 = nm::ip4_info(&path).await;
+                            (path, ip4)
                         },
-                        |(result, path)| Message::NetworksLoaded(result, path),
+                        |(path, ip4)| Message::Ip4Loaded(path, ip4),
                     );
                 }
                 Task::none()
             }
+            Message::Ip4Loaded(for_device, info) => {
+                if let State::Loaded {
+                    devices,
+                    selected_device,
+                    ip4,
+                    ..
+                } = &mut self.state
+                    && devices[*selected_device].path == for_device
+                {
+                    *ip4 = info;
+                }
+                Task::none()
+            }
+            Message::NetworkChanged => {
+                if let State::Loaded {
+                    devices,
+                    selected_device,
+                    ..
+                } = &self.state
+                {
+                    // Tunnels coming up or down change the active connections too
+                    return Task::batch([
+                        self.scan_selected(devices, *selected_device),
+                        Task::perform(nm::list_wireguard(), Message::WireGuardLoaded),
+                    ]);
+                }
+                Task::none()
+            }
+            Message::PreferDevice(ssid, prefer) => {
+                let key = format!("prefer_device.{ssid}");
+                let result = if prefer {
+                    let Some((devices, selected_device)) = self.state.device_info() else {
+                        return Task::none();
+                    };
+                    let interface = devices[selected_device].interface.clone();
+                    let result = config::save(&key, &interface);
+                    self.config.preferred_devices.insert(ssid, interface);
+                    result
+                } else {
+                    self.config.preferred_devices.remove(&ssid);
+                    config::remove(&key)
+                };
+                if let Err(e) = result {
+                    eprintln!("netman: {e}");
+                }
+                Task::none()
+            }
+            Message::ConnectivityChanged(connectivity) => {
+                self.connectivity = connectivity;
+                Task::none()
+            }
+            Message::ConnectivityLoaded(result) => {
+                // Only used for a warning, unknown if it can't be read
+                self.connectivity = result.unwrap_or(nm::Connectivity::Unknown);
+                Task::none()
+            }
+            Message::WireGuardLoaded(result) => {
+                // Only an extra, not worth an error screen if it fails
+                if let Ok(tunnels) = result {
+                    self.wireguard = tunnels;
+                }
+                Task::none()
+            }
+            Message::ToggleWireGuard(path, up) => {
+                let Some(tunnel) = self.wireguard.iter().find(|t| t.path == path) else {
+                    return Task::none();
+                };
+                Task::perform(
+                    nm::set_wireguard(tunnel.clone(), up),
+                    Message::WireGuardToggled,
+                )
+            }
+            Message::WireGuardToggled(result) => {
+                if let Err(e) = result
+                    && let State::Loaded { note, .. } = &mut self.state
+                {
+                    *note = Some(e);
+                }
+                Task::perform(nm::list_wireguard(), Message::WireGuardLoaded)
+            }
+            Message::Share(ssid) => {
+                self.share = Some(ShareDialog {
+                    ssid: ssid.clone(),
+                    code: None,
+                });
+                Task::perform(nm::shared_network(ssid.clone()), move |result| {
+                    Message::ShareLoaded(ssid.clone(), result)
+                })
+            }
+            Message::ShareLoaded(ssid, result) => {
+                if let Some(share) = &mut self.share
+                    && share.ssid == ssid
+                {
+                    share.code = Some(result.and_then(|network| {
+                        let code = share::Code::new(&share::payload(&network))?;
+                        Ok((network, code))
+                    }));
+                }
+                Task::none()
+            }
+            Message::CloseShare => {
+                self.share = None;
+                Task::none()
+            }
+            Message::Forget(ssid) => {
+                if self.blocked_by_wifi_off() {
+                    return Task::none();
+                }
+                Task::perform(nm::forget_network(ssid), Message::Forgotten)
+            }
+            Message::SetAutoconnect(ssid, enabled) => {
+                Task::perform(nm::set_autoconnect(ssid, enabled), Message::AutoconnectSet)
+            }
+            Message::SetMetered(ssid, metered) => {
+                Task::perform(nm::set_metered(ssid, metered), Message::MeteredSet)
+            }
+            Message::Forgotten(result)
+            | Message::AutoconnectSet(result)
+            | Message::MeteredSet(result)
+            | Message::Unpinned(result) => {
+                if let State::SavedNetworks { error, .. } = &mut self.state {
+                    *error = result.err();
+                    return Task::perform(nm::list_saved_networks(), Message::SavedNetworksLoaded);
+                }
+                let State::Loaded {
+                    devices,
+                    selected_device,
+                    note,
+                    ..
+                } = &mut self.state
+                else {
+                    return Task::none();
+                };
+                if let Err(e) = result {
+                    *note = Some(e);
+                }
+                let (devices, selected) = (devices.clone(), *selected_device);
+                self.scan_selected(&devices, selected)
+            }
             Message::DevicesChanged => {
-                *self = App::Loading;
-                Task::perform(nm::list_wifi_devices(), Message::DevicesLoaded)
+                self.state = State::Loading;
+                Task::perform(self.backend.list_wifi_devices(), Message::DevicesLoaded)
+            }
+            Message::PrimaryConnectionChanged => {
+                Task::perform(nm::default_route_device(), Message::DefaultRouteLoaded)
+            }
+            Message::DefaultRouteLoaded(result) => {
+                // Only an annotation, not worth an error screen if it fails
+                if let Ok(default_route) = result
+                    && let State::Loaded { devices, .. } = &mut self.state
+                {
+                    for device in devices {
+                        device.is_default_route =
+                            default_route.as_deref() == Some(device.path.as_str());
+                    }
+                }
+                Task::none()
             }
             Message::Back => {
-                if let App::Error { .. } = self
-                    && let Some((devices, selected)) = self.device_info()
+                if let State::Error { .. } = self.state
+                    && let Some((devices, selected)) = self.state.device_info()
                 {
                     let task = self.scan_selected(&devices, selected);
-                    *self = App::Loaded {
-                        devices,
-                        selected_device: selected,
-                        networks: Vec::new(),
-                        connecting_ssid: None,
-                        password: String::new(),
-                        wifi_enabled: true,
-                    };
+                    self.state = State::loaded(devices, selected);
                     return task;
                 }
                 Task::none()
             }
+            Message::Retry => {
+                let State::Error {
+                    retry: Some(retry), ..
+                } = &self.state
+                else {
+                    return Task::none();
+                };
+                let retry = retry.clone();
+                let Some((devices, selected)) = self.state.device_info() else {
+                    return Task::none();
+                };
+                match retry {
+                    Retry::Connect(request) => {
+                        self.state = connecting(&self.config, devices, selected, *request);
+                        Task::none()
+                    }
+                    Retry::Disconnect(ssid) => {
+                        let path = devices[selected].path.clone();
+                        self.state = State::Disconnecting {
+                            devices,
+                            selected_device: selected,
+                            ssid: ssid.clone(),
+                        };
+                        Task::perform(self.backend.disconnect(path, ssid), Message::Disconnected)
+                    }
+                }
+            }
+            Message::Reload => {
+                // Start over, the preserved device list may be stale too
+                self.probe = Probe::Idle;
+                self.strength = StrengthHistory::default();
+                self.state = State::Loading;
+                Task::perform(self.backend.list_wifi_devices(), Message::DevicesLoaded)
+            }
             Message::Refresh => {
-                if let Some((devices, selected)) = self.device_info() {
+                if let Some((devices, selected)) = self.state.device_info() {
                     let task = self.scan_selected(&devices, selected);
-                    *self = App::Loaded {
-                        devices,
-                        selected_device: selected,
-                        networks: Vec::new(),
-                        connecting_ssid: None,
-                        password: String::new(),
-                        wifi_enabled: true,
-                    };
+                    self.state = State::loaded(devices, selected);
                     return task;
                 }
                 Task::none()
             }
             Message::Disconnect => {
-                if let Some((devices, selected)) = self.device_info() {
+                // The connection the user sees, in case the device has more
+                let ssid = match &self.state {
+                    State::Loaded { networks, .. } => networks
+                        .iter()
+                        .find(|n| n.is_connected)
+                        .map(|n| n.ssid.clone()),
+                    _ => None,
+                };
+                if let Some((devices, selected)) = self.state.device_info() {
                     let path = devices[selected].path.clone();
-                    *self = App::Disconnecting {
+                    self.state = State::Disconnecting {
                         devices,
                         selected_device: selected,
+                        ssid: ssid.clone(),
                     };
                     return Task::perform(
-                        async move { nm::disconnect(&path).await },
+                        self.backend.disconnect(path, ssid),
                         Message::Disconnected,
                     );
                 }
@@ -394,137 +2064,504 @@ impl App {
             }
             Message::Disconnected(result) => {
                 if let Err(e) = result {
-                    self.goto_error(e);
+                    let ssid = match &self.state {
+                        State::Disconnecting { ssid, .. } => ssid.clone(),
+                        _ => None,
+                    };
+                    self.goto_error_retrying(e, Retry::Disconnect(ssid));
                     return Task::none();
                 }
-                if let Some((devices, selected)) = self.device_info() {
-                    let task = self.scan_selected(&devices, selected);
-                    *self = App::Loaded {
-                        devices,
-                        selected_device: selected,
-                        networks: Vec::new(),
+                let notify = match &self.state {
+                    State::Disconnecting {
+                        ssid: Some(ssid), ..
+                    } => self.notify("Disconnected", format!("Disconnected from {ssid}")),
+                    _ => Task::none(),
+                };
+                if self.config.menu_mode {
+                    return notify.chain(iced::exit());
+                }
+                Task::batch([notify, self.connection_changed()])
+            }
+            Message::ConnectCancelled => self.connection_changed(),
+            Message::Connect(ssid) => self.start_connect(ssid, None, None),
+            Message::ConnectOnBand(ssid, band) => self.start_connect(ssid, Some(band), None),
+            Message::ConnectToAp(ssid, band, bssid) => self.start_connect(ssid, band, Some(bssid)),
+            Message::Unpin(ssid) => Task::perform(nm::unpin_bssid(ssid), Message::Unpinned),
+            Message::HighlightUp | Message::HighlightDown => {
+                // The highlight is on the list, not the password entry
+                let rows = match &self.state {
+                    State::Loaded {
+                        networks,
                         connecting_ssid: None,
-                        password: String::new(),
-                        wifi_enabled: true,
+                        ..
+                    } => self.list_rows(networks).len(),
+                    _ => return Task::none(),
+                };
+                if let State::Loaded { highlighted, .. } = &mut self.state {
+                    let up = matches!(message, Message::HighlightUp);
+                    *highlighted = match *highlighted {
+                        _ if rows == 0 => None,
+                        None if up => Some(rows - 1),
+                        None => Some(0),
+                        Some(row) if up => Some(row.min(rows - 1).saturating_sub(1)),
+                        Some(row) => Some((row + 1).min(rows - 1)),
                     };
-                    return task;
                 }
                 Task::none()
             }
-            Message::Connect(ssid) => {
-                if let App::Loaded {
-                    devices,
-                    selected_device,
-                    networks,
-                    connecting_ssid,
-                    password,
-                    ..
-                } = self
-                {
-                    // Open or saved networks: connect immediately (no password needed)
-                    if let Some(net) = networks.iter().find(|n| n.ssid == ssid)
-                        && (net.security == "Open" || net.is_saved)
-                    {
-                        let net = net.clone();
-                        let devs = devices.clone();
-                        let sel = *selected_device;
-                        *self = App::Connecting {
-                            devices: devs,
-                            selected_device: sel,
-                        };
-                        return Task::perform(nm::connect(net, String::new()), Message::Connected);
-                    }
-                    *connecting_ssid = Some(ssid);
-                    *password = String::new();
-                    return iced::widget::operation::focus("password-input");
+            Message::ActivateHighlighted => {
+                let action = match &self.state {
+                    State::Loaded {
+                        networks,
+                        connecting_ssid: None,
+                        highlighted: Some(row),
+                        switching,
+                        ..
+                    } => self
+                        .list_rows(networks)
+                        .get(*row)
+                        .map(|(network, _)| App::row_action(network, self.show_bands, *switching)),
+                    _ => None,
+                };
+                match action {
+                    Some(action) => self.update_state(action),
+                    None => Task::none(),
                 }
+            }
+            Message::ShowBands(show) => {
+                self.show_bands = show;
                 Task::none()
             }
-            Message::PasswordChanged(pw) => {
-                if let App::Loaded { password, .. } = self {
-                    *password = pw;
-                }
+            Message::ShowAllAps(show) => {
+                self.show_all_aps = show;
                 Task::none()
             }
-            Message::SubmitConnect => {
-                if let App::Loaded {
+            Message::AlwaysOnTop(on_top) => {
+                self.config.always_on_top = on_top;
+                if let Err(e) = config::save("always_on_top", &on_top.to_string()) {
+                    eprintln!("netman: {e}");
+                }
+                window::latest().and_then(move |id| window::set_level(id, window_level(on_top)))
+            }
+            Message::Reconnect(ssid) => {
+                if self.blocked_by_wifi_off() {
+                    return Task::none();
+                }
+                if let State::Loaded {
+                    devices,
+                    selected_device,
+                    networks,
+                    ..
+                } = &self.state
+                    && let Some(net) = networks.iter().find(|n| n.ssid == ssid)
+                {
+                    self.state = connecting(
+                        &self.config,
+                        devices.clone(),
+                        *selected_device,
+                        ConnectRequest {
+                            network: net.clone(),
+                            password: String::new(),
+                            options: nm::ConnectOptions::default(),
+                            reconnect: true,
+                            backend: self.backend.clone(),
+                        },
+                    );
+                }
+                Task::none()
+            }
+            Message::PasswordChanged(pw) => {
+                if let State::Loaded {
+                    password,
+                    password_error,
+                    ..
+                } = &mut self.state
+                {
+                    *password = pw;
+                    *password_error = None;
+                }
+                Task::none()
+            }
+            Message::TogglePasswordVisibility => {
+                if let State::Loaded {
+                    password_visible, ..
+                } = &mut self.state
+                {
+                    *password_visible = !*password_visible;
+                }
+                Task::none()
+            }
+            Message::IdentityChanged(identity) => {
+                if let State::Loaded { options, .. } = &mut self.state {
+                    options.identity = identity;
+                }
+                Task::none()
+            }
+            Message::AnonymousIdentityChanged(identity) => {
+                if let State::Loaded {
+                    advanced: Some(draft),
+                    ..
+                } = &mut self.state
+                {
+                    draft.anonymous_identity = identity;
+                }
+                Task::none()
+            }
+            Message::EditAdvanced => {
+                if let State::Loaded {
+                    options, advanced, ..
+                } = &mut self.state
+                {
+                    *advanced = Some(options.clone());
+                }
+                Task::none()
+            }
+            Message::KeyMgmtSelected(selected) => {
+                if let State::Loaded {
+                    advanced: Some(draft),
+                    ..
+                } = &mut self.state
+                {
+                    draft.key_mgmt = selected;
+                }
+                Task::none()
+            }
+            Message::MeteredToggled(metered) => {
+                if let State::Loaded {
+                    advanced: Some(draft),
+                    ..
+                } = &mut self.state
+                {
+                    draft.metered = metered;
+                }
+                Task::none()
+            }
+            Message::PinInterfaceToggled(pin) => {
+                if let State::Loaded {
+                    devices,
+                    selected_device,
+                    advanced: Some(draft),
+                    ..
+                } = &mut self.state
+                {
+                    draft.interface_name = pin.then(|| devices[*selected_device].interface.clone());
+                }
+                Task::none()
+            }
+            Message::ApplyAdvanced => {
+                if let State::Loaded {
+                    options, advanced, ..
+                } = &mut self.state
+                    && let Some(draft) = advanced.take()
+                {
+                    *options = draft;
+                    return iced::widget::operation::focus("password-input");
+                }
+                Task::none()
+            }
+            Message::CancelAdvanced => {
+                if let State::Loaded { advanced, .. } = &mut self.state
+                    && advanced.take().is_some()
+                {
+                    return iced::widget::operation::focus("password-input");
+                }
+                Task::none()
+            }
+            Message::SubmitConnect => {
+                if self.blocked_by_wifi_off() {
+                    return Task::none();
+                }
+                if let State::Loaded {
                     devices,
                     selected_device,
                     networks,
                     connecting_ssid: Some(ssid),
+                    connecting_band,
                     password,
+                    password_error,
+                    options,
                     ..
-                } = self
-                    && let Some(net) = networks.iter().find(|n| n.ssid == *ssid)
+                } = &mut self.state
+                    && let Some(net) = find_network(networks, ssid, *connecting_band)
                 {
+                    // A saved profile is activated as it is, unless replaced
+                    if (!net.is_saved || options.replace_saved)
+                        && let Err(e) = nm::check_password(options.key_mgmt_for(net), password)
+                    {
+                        *password_error = Some(e);
+                        return Task::none();
+                    }
                     let net = net.clone();
                     let pw = password.clone();
+                    let options = options.clone();
                     let devs = devices.clone();
                     let sel = *selected_device;
-                    *self = App::Connecting {
-                        devices: devs,
-                        selected_device: sel,
-                    };
-                    return Task::perform(nm::connect(net, pw), Message::Connected);
+                    self.state = connecting(
+                        &self.config,
+                        devs,
+                        sel,
+                        ConnectRequest {
+                            network: net,
+                            password: pw,
+                            options,
+                            reconnect: false,
+                            backend: self.backend.clone(),
+                        },
+                    );
+                    return Task::none();
                 }
                 Task::none()
             }
             Message::CancelConnect => {
-                if let App::Loaded {
+                if self.share.take().is_some() {
+                    return Task::none();
+                }
+                if let State::Loaded {
                     connecting_ssid,
                     password,
+                    password_visible,
+                    advanced,
+                    switching,
+                    highlighted,
                     ..
-                } = self
+                } = &mut self.state
                 {
+                    if advanced.take().is_some() {
+                        return iced::widget::operation::focus("password-input");
+                    }
+                    if *switching {
+                        *switching = false;
+                        return Task::none();
+                    }
                     if connecting_ssid.is_some() {
                         *connecting_ssid = None;
                         *password = String::new();
+                        *password_visible = false;
+                        return Task::none();
+                    }
+                    if highlighted.take().is_some() {
                         return Task::none();
                     }
                     return iced::exit();
                 }
-                if let App::Error { .. } = self
-                    && let Some((devices, selected)) = self.device_info()
+                if let State::Error { .. }
+                | State::JoinOther { .. }
+                | State::ActiveConnections { .. }
+                | State::SavedNetworks { .. }
+                | State::EditIp { .. } = self.state
+                    && let Some((devices, selected)) = self.state.device_info()
                 {
                     let task = self.scan_selected(&devices, selected);
-                    *self = App::Loaded {
+                    self.state = State::loaded(devices, selected);
+                    return task;
+                }
+                // Leaving `Connecting` drops the connect subscription. Tear down
+                // whatever NM activated so far. There may be nothing to
+                // deactivate yet, so the result is ignored.
+                if let State::Connecting { .. } = self.state
+                    && let Some((devices, selected)) = self.state.device_info()
+                {
+                    let path = devices[selected].path.clone();
+                    self.state = State::Disconnecting {
                         devices,
                         selected_device: selected,
-                        networks: Vec::new(),
-                        connecting_ssid: None,
-                        password: String::new(),
-                        wifi_enabled: true,
+                        ssid: None,
                     };
-                    return task;
+                    let disconnect = self.backend.disconnect(path, None);
+                    return Task::perform(
+                        async move {
+                            let _ = disconnect.await;
+                        },
+                        |()| Message::ConnectCancelled,
+                    );
                 }
+                // Disconnecting can't be cancelled, the view says so
                 Task::none()
             }
-            Message::Connected(result) => {
-                if let Err(e) = result {
-                    self.goto_error(e);
-                    return Task::none();
-                }
-                if let Some((devices, selected)) = self.device_info() {
-                    let task = self.scan_selected(&devices, selected);
-                    *self = App::Loaded {
+            Message::JoinOther => {
+                if let State::Loaded { .. } = self.state
+                    && let Some((devices, selected_device)) = self.state.device_info()
+                {
+                    self.state = State::JoinOther {
                         devices,
-                        selected_device: selected,
-                        networks: Vec::new(),
-                        connecting_ssid: None,
+                        selected_device,
+                        ssid: String::new(),
+                        security: self.config.join_security,
                         password: String::new(),
-                        wifi_enabled: true,
+                        error: None,
                     };
-                    return task;
+                    return iced::widget::operation::focus("join-ssid-input");
+                }
+                Task::none()
+            }
+            Message::JoinSsidChanged(new_ssid) => {
+                if let State::JoinOther { ssid, .. } = &mut self.state {
+                    *ssid = new_ssid;
+                }
+                Task::none()
+            }
+            Message::JoinSecuritySelected(selected) => {
+                if let State::JoinOther { security, .. } = &mut self.state {
+                    *security = selected;
+                }
+                Task::none()
+            }
+            Message::JoinPasswordChanged(pw) => {
+                if let State::JoinOther { password, .. } = &mut self.state {
+                    *password = pw;
+                }
+                Task::none()
+            }
+            Message::SubmitJoin => {
+                if let State::JoinOther {
+                    devices,
+                    selected_device,
+                    ssid,
+                    security,
+                    password,
+                    error,
+                } = &mut self.state
+                {
+                    if ssid.is_empty() {
+                        *error = Some("Enter the network name".to_string());
+                        return Task::none();
+                    }
+                    if ssid.len() > 32 {
+                        *error = Some("Network names are at most 32 bytes".to_string());
+                        return Task::none();
+                    }
+                    if let Err(e) =
+                        nm::check_password(nm::KeyMgmt::for_security(security), password)
+                    {
+                        *error = Some(e);
+                        return Task::none();
+                    }
+
+                    let device_path = devices[*selected_device].path.clone();
+                    let network = nm::Network {
+                        ssid: ssid.clone(),
+                        ssid_bytes: ssid.as_bytes().to_vec(),
+                        strength: 0,
+                        security: security.to_string(),
+                        is_connected: false,
+                        is_primary: false,
+                        is_saved: false,
+                        autoconnect: None,
+                        metered: false,
+                        pinned_bssid: None,
+                        ap_count: 0,
+                        frequency: 0,
+                        bssid: String::new(),
+                        access_points: Vec::new(),
+                        // No specific access point, NM picks one
+                        ap_path: "/".to_string(),
+                        device_path,
+                    };
+                    let password = if *security == "Open" {
+                        String::new()
+                    } else {
+                        password.clone()
+                    };
+                    self.state = connecting(
+                        &self.config,
+                        devices.clone(),
+                        *selected_device,
+                        ConnectRequest {
+                            network,
+                            password,
+                            // Not seen in a scan, NM has to probe for it
+                            options: nm::ConnectOptions {
+                                hidden: true,
+                                ..Default::default()
+                            },
+                            reconnect: false,
+                            backend: self.backend.clone(),
+                        },
+                    );
+                }
+                Task::none()
+            }
+            Message::ConnectProgress(new_phase) => {
+                if let State::Connecting { phase, .. } = &mut self.state {
+                    *phase = new_phase;
                 }
                 Task::none()
             }
+            Message::Connected(result) => {
+                if let Err(
+                    e @ (nm::ConnectError::SavedSecretsRejected
+                    | nm::ConnectError::PasswordRejected),
+                ) = &result
+                    && let State::Connecting {
+                        devices,
+                        selected_device,
+                        request,
+                        ..
+                    } = &self.state
+                {
+                    // Ask for the password again, with the rejected one to
+                    // fix typos in. Submitting it replaces the saved profile.
+                    let replace_saved = matches!(e, nm::ConnectError::SavedSecretsRejected);
+                    let ssid = request.network.ssid.clone();
+                    let request = request.clone();
+                    let (devices, selected) = (devices.clone(), *selected_device);
+                    let task = self.scan_selected(&devices, selected);
+                    let mut state = State::loaded(devices, selected);
+                    if let State::Loaded {
+                        networks,
+                        connecting_ssid,
+                        password,
+                        options,
+                        note,
+                        ..
+                    } = &mut state
+                    {
+                        *note = Some(if replace_saved {
+                            format!("The saved password for {ssid} seems wrong, re-enter it")
+                        } else {
+                            format!("Wrong password for {ssid}, try again")
+                        });
+                        // Shows the password entry until the scan is done
+                        *networks = vec![request.network];
+                        *connecting_ssid = Some(ssid);
+                        *password = request.password;
+                        *options = request.options;
+                        options.replace_saved = replace_saved;
+                    }
+                    self.state = state;
+                    return Task::batch([task, iced::widget::operation::focus("password-input")]);
+                }
+                let request = match &self.state {
+                    State::Connecting { request, .. } => Some(request.clone()),
+                    _ => None,
+                };
+                let ssid = request
+                    .as_ref()
+                    .map(|request| request.network.ssid.clone())
+                    .unwrap_or_default();
+                if let Err(e) = result {
+                    let notify = self.notify("Connection failed", format!("{ssid}: {e}"));
+                    match request {
+                        Some(request) => self
+                            .goto_error_retrying(e.to_string(), Retry::Connect(Box::new(request))),
+                        None => self.goto_error(e.to_string()),
+                    }
+                    return notify;
+                }
+                let notify = self.notify("Connected", format!("Connected to {ssid}"));
+                self.connected_since = Some((ssid, std::time::Instant::now()));
+                if self.config.menu_mode {
+                    return notify.chain(iced::exit());
+                }
+                Task::batch([notify, self.connection_changed()])
+            }
             Message::WifiEnabledChanged => {
-                Task::perform(nm::get_wifi_enabled(), Message::WifiEnabledLoaded)
+                Task::perform(self.backend.get_wifi_enabled(), Message::WifiEnabledLoaded)
             }
             Message::WifiEnabledLoaded(result) => {
                 match result {
                     Ok(enabled) => {
-                        if let App::Loaded { wifi_enabled, .. } = self {
+                        if let State::Loaded { wifi_enabled, .. } = &mut self.state {
                             *wifi_enabled = enabled;
                         }
                     }
@@ -533,154 +2570,1956 @@ impl App {
                 Task::none()
             }
             Message::ToggleWifi(enabled) => {
-                Task::perform(nm::set_wifi_enabled(enabled), Message::WifiToggled)
+                Task::perform(self.backend.set_wifi_enabled(enabled), Message::WifiToggled)
             }
             Message::WifiToggled(result) => {
                 match result {
                     Ok(enabled) => {
                         if enabled {
                             // WiFi turned on — reload devices and networks
-                            *self = App::Loading;
-                            return Task::perform(nm::list_wifi_devices(), Message::DevicesLoaded);
+                            self.state = State::Loading;
+                            return Task::perform(
+                                self.backend.list_wifi_devices(),
+                                Message::DevicesLoaded,
+                            );
                         }
-                        if let App::Loaded {
+                        if let State::Loaded {
                             wifi_enabled,
                             networks,
                             connecting_ssid,
                             password,
+                            password_visible,
                             ..
-                        } = self
+                        } = &mut self.state
                         {
                             *wifi_enabled = false;
                             *networks = Vec::new();
                             *connecting_ssid = None;
                             *password = String::new();
+                            *password_visible = false;
                         }
                     }
                     Err(e) => self.goto_error(e),
                 }
                 Task::none()
             }
-        }
-    }
-
-    fn view(&self) -> Element<'_, Message> {
-        let content: Element<Message> = match self {
-            App::Loading => column![text("Scanning...").size(18)].into(),
-            App::Connecting { .. } => column![text("Connecting...").size(18)].into(),
-            App::Disconnecting { .. } => column![text("Disconnecting...").size(18)].into(),
-            App::Loaded {
-                devices,
-                selected_device,
-                networks,
-                connecting_ssid,
-                password,
-                wifi_enabled,
-            } => {
-                let mut header = row![text("WiFi Networks").size(22),]
-                    .align_y(iced::Alignment::Center)
-                    .spacing(10)
-                    .padding(6);
-
-                if devices.len() > 1 {
-                    header = header.push(
-                        pick_list(
-                            devices.as_slice(),
-                            Some(&devices[*selected_device]),
-                            Message::DeviceSelected,
-                        )
-                        .text_size(14),
+            Message::DismissScanNotice => {
+                self.scan_notice = ScanNotice::Dismissed;
+                Task::none()
+            }
+            // Only here to re-render time-dependent parts of the view
+            Message::Tick => Task::none(),
+            Message::Tray(tray::Event::Ready(ready)) => {
+                self.tray_active = ready;
+                // Pushed again once this message is handled
+                self.tray_status = None;
+                Task::none()
+            }
+            Message::Tray(tray::Event::Action(action)) => match action {
+                tray::Action::Open => window::latest().and_then(|id| {
+                    window::set_mode(id, window::Mode::Windowed).chain(window::gain_focus(id))
+                }),
+                tray::Action::ToggleWifi => match self.state {
+                    State::Loaded { wifi_enabled, .. } => {
+                        self.update_state(Message::ToggleWifi(!wifi_enabled))
+                    }
+                    _ => Task::none(),
+                },
+                tray::Action::Disconnect => self.update_state(Message::Disconnect),
+            },
+            Message::CloseRequested(id) => {
+                // Without a tray icon there'd be no way to get the window back
+                if self.tray_active {
+                    window::set_mode(id, window::Mode::Hidden)
+                } else {
+                    iced::exit()
+                }
+            }
+            Message::TestConnectivity => {
+                self.probe = Probe::Running;
+                Task::perform(
+                    probe::tcp_connect(self.config.probe_target.clone()),
+                    Message::ConnectivityTested,
+                )
+            }
+            Message::StrengthSampled(result) => {
+                // A failed sample just leaves a gap
+                if let Ok(sample) = result {
+                    self.strength.push(sample);
+                }
+                Task::none()
+            }
+            Message::StrengthUpdated(ap_path, strength) => {
+                if let State::Loaded { networks, .. } = &mut self.state
+                    && let Some(network) = networks.iter_mut().find(|n| n.ap_path == ap_path)
+                {
+                    network.strength = strength;
+                }
+                Task::none()
+            }
+            Message::BitrateUpdated(device_path, rate) => {
+                if let State::Loaded {
+                    devices,
+                    selected_device,
+                    bitrate,
+                    ..
+                } = &mut self.state
+                    && devices[*selected_device].path == device_path
+                {
+                    *bitrate = rate;
+                }
+                Task::none()
+            }
+            Message::TargetStrengthSampled(ssid, strength) => {
+                if let State::Loaded { networks, .. } = &mut self.state
+                    && let Some(network) = networks.iter_mut().find(|n| n.ssid_bytes == ssid)
+                {
+                    network.strength = strength;
+                }
+                Task::none()
+            }
+            Message::ShowActiveConnections => {
+                if let State::Loaded { .. } = self.state
+                    && let Some((devices, selected_device)) = self.state.device_info()
+                {
+                    self.state = State::ActiveConnections {
+                        devices,
+                        selected_device,
+                        connections: None,
+                        error: None,
+                    };
+                    return Task::perform(
+                        nm::list_active_connections(),
+                        Message::ActiveConnectionsLoaded,
                     );
                 }
-
-                header = header.push(iced::widget::space::horizontal());
-
-                if *wifi_enabled {
-                    header = header
-                        .push(button("Refresh").on_press(Message::Refresh))
-                        .push(button("Turn off").on_press(Message::ToggleWifi(false)));
-                } else {
-                    header = header.push(button("Turn on").on_press(Message::ToggleWifi(true)));
+                Task::none()
+            }
+            Message::ShowSavedNetworks => {
+                if let State::Loaded { .. } = self.state
+                    && let Some((devices, selected_device)) = self.state.device_info()
+                {
+                    self.state = State::SavedNetworks {
+                        devices,
+                        selected_device,
+                        networks: None,
+                        error: None,
+                    };
+                    return Task::perform(nm::list_saved_networks(), Message::SavedNetworksLoaded);
                 }
-
-                if !wifi_enabled {
-                    column![header, text("WiFi is disabled").size(16)]
-                        .spacing(15)
-                        .into()
-                } else if networks.is_empty() {
-                    column![header, text("Scanning...").size(16)]
-                        .spacing(15)
-                        .into()
-                } else {
-                    let list = networks.iter().fold(column![].spacing(4), |col, network| {
-                        let is_entering_password =
-                            connecting_ssid.as_deref() == Some(&network.ssid);
-
-                        let ssid_text = text(&network.ssid).size(16);
-                        let info =
-                            text(format!("{}%  {}", network.strength, network.security)).size(13);
-
-                        let network_row = if is_entering_password {
-                            // Password input row
-                            let input = text_input("Password", password)
-                                .id("password-input")
-                                .on_input(Message::PasswordChanged)
-                                .on_submit(Message::SubmitConnect)
-                                .secure(true)
-                                .size(14)
-                                .width(iced::Fill);
-
-                            row![input].align_y(iced::Alignment::Center).padding(6)
-                        } else {
-                            let mut r = row![
-                                column![ssid_text, info].spacing(2),
-                                iced::widget::space::horizontal(),
-                            ]
-                            .align_y(iced::Alignment::Center)
-                            .padding(6);
-
-                            if network.is_connected {
-                                r = r.push(button("Disconnect").on_press(Message::Disconnect));
-                            } else {
-                                r = r.push(
-                                    button("Connect")
-                                        .on_press(Message::Connect(network.ssid.clone())),
-                                );
-                            }
-
-                            r
-                        };
-
-                        col.push(network_row)
-                            .push(iced::widget::rule::horizontal(1))
-                    });
-
-                    let thin_scrollbar = scrollable::Scrollbar::new()
-                        .width(6)
-                        .scroller_width(6)
-                        .spacing(0);
-
-                    column![
-                        header,
-                        scrollable(list).direction(scrollable::Direction::Vertical(thin_scrollbar)),
-                    ]
-                    .spacing(15)
-                    .into()
+                Task::none()
+            }
+            Message::SavedNetworksLoaded(result) => {
+                if let State::SavedNetworks {
+                    networks, error, ..
+                } = &mut self.state
+                {
+                    match result {
+                        Ok(list) => *networks = Some(list),
+                        Err(e) => *error = Some(e),
+                    }
                 }
+                Task::none()
             }
-            App::Error {
-                message, devices, ..
-            } => {
-                let mut col = column![text("Error").size(22), text(message).size(14),].spacing(10);
-
-                if devices.is_some() {
-                    col = col.push(button("Back").on_press(Message::Back));
+            Message::ActiveConnectionsLoaded(result) => {
+                if let State::ActiveConnections {
+                    connections, error, ..
+                } = &mut self.state
+                {
+                    match result {
+                        Ok(list) => *connections = Some(list),
+                        Err(e) => *error = Some(e),
+                    }
                 }
-
-                col.into()
+                Task::none()
             }
-        };
+            Message::Deactivate(path) => {
+                if let State::ActiveConnections { error, .. } = &mut self.state {
+                    *error = None;
+                    return Task::perform(nm::deactivate(path), Message::Deactivated);
+                }
+                Task::none()
+            }
+            Message::Deactivated(result) => {
+                if let State::ActiveConnections { error, .. } = &mut self.state {
+                    if let Err(e) = result {
+                        *error = Some(e);
+                    }
+                    return Task::perform(
+                        nm::list_active_connections(),
+                        Message::ActiveConnectionsLoaded,
+                    );
+                }
+                Task::none()
+            }
+            Message::StartSwitch | Message::CancelSwitch => {
+                if let State::Loaded { switching, .. } = &mut self.state {
+                    *switching = matches!(message, Message::StartSwitch);
+                }
+                Task::none()
+            }
+            Message::ForgetAndConnect(ssid) => {
+                if self.blocked_by_wifi_off() {
+                    return Task::none();
+                }
+                let State::Loaded {
+                    devices,
+                    selected_device,
+                    networks,
+                    switching: true,
+                    ..
+                } = &self.state
+                else {
+                    return Task::none();
+                };
+                let (Some(current), Some(target)) = (
+                    networks.iter().find(|n| n.is_connected),
+                    networks.iter().find(|n| n.ssid == ssid),
+                ) else {
+                    return Task::none();
+                };
+                let current_bytes = current.ssid_bytes.clone();
+                let current = current.ssid.clone();
+                let mut target = target.clone();
+                let device_path = devices[*selected_device].path.clone();
+                self.state = State::Disconnecting {
+                    devices: devices.clone(),
+                    selected_device: *selected_device,
+                    ssid: Some(current.clone()),
+                };
+                let disconnect = self.backend.disconnect(device_path, Some(current.clone()));
+                Task::perform(
+                    async move {
+                        let result = async {
+                            disconnect.await?;
+                            nm::forget_network(current_bytes).await
+                        }
+                        .await;
+                        target.is_connected = false;
+                        (result, target)
+                    },
+                    |(result, target)| Message::Forgot(result, target),
+                )
+            }
+            Message::Forgot(result, target) => {
+                if let Err(e) = result {
+                    self.goto_error(e);
+                    return Task::none();
+                }
+                self.probe = Probe::Idle;
+                self.strength = StrengthHistory::default();
+                let Some((devices, selected_device)) = self.state.device_info() else {
+                    return Task::none();
+                };
+                if target.connects_instantly() {
+                    self.state = connecting(
+                        &self.config,
+                        devices,
+                        selected_device,
+                        ConnectRequest {
+                            network: target,
+                            password: String::new(),
+                            options: nm::ConnectOptions::default(),
+                            reconnect: false,
+                            backend: self.backend.clone(),
+                        },
+                    );
+                    return Task::none();
+                }
+                // Ask for the password once the network shows up in the new scan
+                let task = self.scan_selected(&devices, selected_device);
+                let mut state = State::loaded(devices, selected_device);
+                if let State::Loaded {
+                    connecting_ssid, ..
+                } = &mut state
+                {
+                    *connecting_ssid = Some(target.ssid);
+                }
+                self.state = state;
+                task
+            }
+            Message::EditIp(ssid) => {
+                if let State::Loaded {
+                    devices,
+                    selected_device,
+                    networks,
+                    ..
+                } = &self.state
+                {
+                    let reactivate = networks
+                        .iter()
+                        .any(|n| n.ssid_bytes == ssid && n.is_connected);
+                    self.state = State::EditIp {
+                        devices: devices.clone(),
+                        selected_device: *selected_device,
+                        ssid: ssid.clone(),
+                        settings: None,
+                        reactivate,
+                        error: None,
+                    };
+                    return Task::perform(nm::load_ip4_settings(ssid), Message::Ip4SettingsLoaded);
+                }
+                Task::none()
+            }
+            Message::Ip4SettingsLoaded(result) => {
+                if let State::EditIp {
+                    settings, error, ..
+                } = &mut self.state
+                {
+                    match result {
+                        Ok(loaded) => *settings = Some(loaded),
+                        Err(e) => *error = Some(e),
+                    }
+                }
+                Task::none()
+            }
+            Message::Ip4ManualToggled(_)
+            | Message::Ip4AddressChanged(_)
+            | Message::Ip4GatewayChanged(_)
+            | Message::Ip4DnsChanged(_) => {
+                // Also edited in the advanced settings of a new profile
+                let settings = match &mut self.state {
+                    State::EditIp {
+                        settings: Some(settings),
+                        ..
+                    } => Some(settings),
+                    State::Loaded {
+                        advanced: Some(draft),
+                        ..
+                    } => Some(&mut draft.ip4),
+                    _ => None,
+                };
+                if let Some(settings) = settings {
+                    match message {
+                        Message::Ip4ManualToggled(manual) => settings.manual = manual,
+                        Message::Ip4AddressChanged(address) => settings.address = address,
+                        Message::Ip4GatewayChanged(gateway) => settings.gateway = gateway,
+                        Message::Ip4DnsChanged(dns) => settings.dns = dns,
+                        _ => {}
+                    }
+                }
+                Task::none()
+            }
+            Message::Ip4ReactivateToggled(value) => {
+                if let State::EditIp { reactivate, .. } = &mut self.state {
+                    *reactivate = value;
+                }
+                Task::none()
+            }
+            Message::SaveIp4 => {
+                if let State::EditIp {
+                    devices,
+                    selected_device,
+                    ssid,
+                    settings: Some(settings),
+                    reactivate,
+                    error,
+                } = &mut self.state
+                {
+                    *error = None;
+                    let reactivate_on = reactivate.then(|| devices[*selected_device].path.clone());
+                    return Task::perform(
+                        nm::save_ip4_settings(ssid.clone(), settings.clone(), reactivate_on),
+                        Message::Ip4Saved,
+                    );
+                }
+                Task::none()
+            }
+            Message::Ip4Saved(result) => {
+                if let State::EditIp { error, .. } = &mut self.state {
+                    if let Err(e) = result {
+                        *error = Some(e);
+                        return Task::none();
+                    }
+                    if let Some((devices, selected)) = self.state.device_info() {
+                        let task = self.scan_selected(&devices, selected);
+                        self.state = State::loaded(devices, selected);
+                        return task;
+                    }
+                }
+                Task::none()
+            }
+            Message::CopyDiagnostics => {
+                let State::Error {
+                    message,
+                    devices,
+                    selected_device,
+                    ..
+                } = &self.state
+                else {
+                    return Task::none();
+                };
+                let interface = devices
+                    .as_ref()
+                    .map(|devices| devices[*selected_device].interface.as_str())
+                    .unwrap_or("none");
+                let report = format!(
+                    "netman {}\nerror: {message}\ninterface: {interface}\nlast action: {}\n",
+                    env!("CARGO_PKG_VERSION"),
+                    self.last_action,
+                );
+                Task::perform(nm::version(), move |version| {
+                    let version = version.unwrap_or_else(|e| e);
+                    format!("{report}NetworkManager: {version}\n")
+                })
+                .then(iced::clipboard::write)
+            }
+            Message::ToggleDeviceMenu => {
+                if let State::Loaded { device_menu, .. } = &mut self.state {
+                    *device_menu = !*device_menu;
+                }
+                Task::none()
+            }
+            Message::ToggleDeviceInfo => {
+                if self.device_capabilities.take().is_some() {
+                    return Task::none();
+                }
+                let Some((devices, selected)) = self.state.device_info() else {
+                    return Task::none();
+                };
+                let path = devices[selected].path.clone();
+                Task::perform(nm::wifi_capabilities(path.clone()), move |result| {
+                    Message::DeviceInfoLoaded(path.clone(), result)
+                })
+            }
+            Message::DeviceInfoLoaded(path, result) => {
+                self.device_capabilities = Some((path, result));
+                Task::none()
+            }
+            Message::DisconnectDevice
+            | Message::SetManaged(_)
+            | Message::MakeDefaultRoute
+            | Message::Reassociate => {
+                let State::Loaded {
+                    devices,
+                    selected_device,
+                    device_menu,
+                    ..
+                } = &mut self.state
+                else {
+                    return Task::none();
+                };
+                *device_menu = false;
+                let path = devices[*selected_device].path.clone();
+                match message {
+                    Message::DisconnectDevice => {
+                        Task::perform(nm::disconnect_device(path), Message::DeviceActionDone)
+                    }
+                    Message::SetManaged(managed) => {
+                        Task::perform(nm::set_managed(path, managed), Message::DeviceActionDone)
+                    }
+                    Message::Reassociate => {
+                        Task::perform(nm::reassociate(path), Message::DeviceActionDone)
+                    }
+                    _ => Task::perform(nm::make_default_route(path), Message::DeviceActionDone),
+                }
+            }
+            Message::DeviceActionDone(result) => match result {
+                // Device properties and the default route may have changed
+                Ok(()) => Task::perform(self.backend.list_wifi_devices(), Message::DevicesLoaded),
+                Err(e) => {
+                    if let State::Loaded { note, .. } = &mut self.state {
+                        *note = Some(e);
+                    }
+                    Task::none()
+                }
+            },
+            Message::ToggleDiagnostics => {
+                self.throughput = match self.throughput {
+                    Some(_) => None,
+                    None => {
+                        self.regdom = regdom::current();
+                        Some(Throughput::default())
+                    }
+                };
+                Task::none()
+            }
+            Message::TrafficSampled(device_path, result) => {
+                match (&mut self.throughput, result) {
+                    (Some(throughput), Ok((rx, tx))) => throughput.push(device_path, rx, tx),
+                    // Most likely not allowed to turn on statistics, no point in
+                    // retrying every second
+                    (Some(throughput), Err(e)) => throughput.error = Some(e),
+                    (None, _) => {}
+                }
+                Task::none()
+            }
+            Message::ConnectivityTested(result) => {
+                self.probe = Probe::Done(result);
+                Task::none()
+            }
+        }
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let content: Element<Message> = match &self.state {
+            State::Loading => column![
+                row![text("WiFi Networks").size(22)]
+                    .align_y(iced::Alignment::Center)
+                    .padding(6),
+                skeleton_rows(),
+            ]
+            .spacing(15)
+            .into(),
+            State::Connecting { phase, .. } => column![
+                text(format!("{phase}...")).size(18),
+                button("Cancel (Esc)").on_press(Message::CancelConnect),
+            ]
+            .spacing(10)
+            .into(),
+            State::Disconnecting { ssid, .. } => column![
+                text(match ssid {
+                    Some(ssid) => format!("Disconnecting from {ssid}..."),
+                    None => "Disconnecting...".to_string(),
+                })
+                .size(18),
+                text("This can't be cancelled").size(12),
+            ]
+            .spacing(10)
+            .into(),
+            State::Loaded {
+                devices,
+                selected_device,
+                networks,
+                connecting_ssid,
+                connecting_band,
+                password,
+                password_error,
+                password_visible,
+                options,
+                advanced,
+                wifi_enabled,
+                ip4,
+                ip6,
+                bitrate,
+                ip4_method,
+                connected_security,
+                connected_ssid,
+                scanned_at,
+                nm_scanned_at,
+                note,
+                device_menu,
+                switching,
+                highlighted,
+            } => {
+                let mut title = row![text("WiFi Networks").size(22)]
+                    .align_y(iced::Alignment::Center)
+                    .spacing(10);
+
+                if devices.len() > 1 {
+                    title = title.push(
+                        pick_list(
+                            devices.as_slice(),
+                            Some(&devices[*selected_device]),
+                            Message::DeviceSelected,
+                        )
+                        .text_size(14),
+                    );
+                }
+
+                let mut header = row![mouse_area(title).on_right_press(Message::ToggleDeviceMenu)]
+                    .align_y(iced::Alignment::Center)
+                    .spacing(10)
+                    .padding(6);
+
+                header = header.push(iced::widget::space::horizontal());
+
+                if *wifi_enabled && let Some(scanned_at) = scanned_at {
+                    header = header.push(
+                        text(format!("updated {} ago", format_age(scanned_at.elapsed()))).size(12),
+                    );
+                }
+
+                let cooldown = nm_scanned_at
+                    .map(|at| SCAN_COOLDOWN.saturating_sub(at.elapsed()))
+                    .filter(|left| !left.is_zero());
+                if *wifi_enabled && let Some(left) = cooldown {
+                    header = header.push(
+                        text(format!(
+                            "scan available in {}s",
+                            left.as_secs_f32().ceil() as u64
+                        ))
+                        .size(12),
+                    );
+                }
+
+                let managed = devices[*selected_device].managed;
+                if *wifi_enabled {
+                    header = header
+                        .push(
+                            checkbox(self.show_bands)
+                                .label("Bands")
+                                .on_toggle(Message::ShowBands)
+                                .text_size(12),
+                        )
+                        .push(
+                            checkbox(self.show_all_aps)
+                                .label("All APs")
+                                .on_toggle(Message::ShowAllAps)
+                                .text_size(12),
+                        )
+                        .push(
+                            button("Join other...")
+                                .on_press_maybe(managed.then_some(Message::JoinOther)),
+                        )
+                        .push(button("Refresh").on_press_maybe(
+                            (managed && cooldown.is_none()).then_some(Message::Refresh),
+                        ))
+                        .push(button("Turn off").on_press(Message::ToggleWifi(false)));
+                } else {
+                    header = header.push(button("Turn on").on_press(Message::ToggleWifi(true)));
+                }
+
+                let mut page = column![header].spacing(15);
+
+                if *device_menu {
+                    let device = &devices[*selected_device];
+                    let managed_button = if device.managed {
+                        button(text("Unmanage").size(13)).on_press(Message::SetManaged(false))
+                    } else {
+                        button(text("Manage").size(13)).on_press(Message::SetManaged(true))
+                    };
+                    page = page.push(
+                        row![
+                            text(&device.interface).size(13),
+                            iced::widget::space::horizontal(),
+                            button(text("Disconnect").size(13)).on_press_maybe(
+                                device.managed.then_some(Message::DisconnectDevice)
+                            ),
+                            managed_button,
+                            button(text("Make default").size(13)).on_press_maybe(
+                                device.managed.then_some(Message::MakeDefaultRoute)
+                            ),
+                            button(text("Info").size(13)).on_press(Message::ToggleDeviceInfo),
+                            button(text("Close").size(13)).on_press(Message::ToggleDeviceMenu),
+                        ]
+                        .align_y(iced::Alignment::Center)
+                        .spacing(6)
+                        .padding(6),
+                    );
+                }
+
+                if let Some((path, result)) = &self.device_capabilities
+                    && *path == devices[*selected_device].path
+                {
+                    page = page.push(device_info_panel(result));
+                }
+
+                if *wifi_enabled && self.scan_notice == ScanNotice::Shown {
+                    page = page.push(
+                        row![
+                            text(
+                                "Scanning is restricted for your user, \
+                                 showing networks NetworkManager already knows about."
+                            )
+                            .size(13)
+                            .width(iced::Fill),
+                            button("Dismiss").on_press(Message::DismissScanNotice),
+                        ]
+                        .align_y(iced::Alignment::Center)
+                        .spacing(10)
+                        .padding(6),
+                    );
+                }
+
+                if networks.iter().any(|n| n.is_connected) {
+                    let warning = match self.connectivity {
+                        nm::Connectivity::None | nm::Connectivity::Limited => {
+                            Some("Connected, but there's no internet access")
+                        }
+                        nm::Connectivity::Portal => {
+                            Some("Connected, but the network wants you to log in first")
+                        }
+                        nm::Connectivity::Unknown | nm::Connectivity::Full => None,
+                    };
+                    if let Some(warning) = warning {
+                        page = page
+                            .push(container(text(warning).size(14).style(text::danger)).padding(6));
+                    }
+                }
+
+                if let Some(note) = note {
+                    page = page.push(text(note).size(13));
+                }
+
+                if !self.wireguard.is_empty() {
+                    let tunnels = self.wireguard.iter().fold(
+                        row![text("WireGuard").size(13)].spacing(12),
+                        |tunnels, tunnel| {
+                            let path = tunnel.path.clone();
+                            tunnels.push(
+                                checkbox(tunnel.active_path.is_some())
+                                    .label(&tunnel.id)
+                                    .on_toggle(move |up| Message::ToggleWireGuard(path.clone(), up))
+                                    .text_size(13),
+                            )
+                        },
+                    );
+                    page = page.push(tunnels.padding([0, 6]));
+                }
+
+                if *switching && let Some(current) = networks.iter().find(|n| n.is_connected) {
+                    page = page.push(
+                        text(format!(
+                            "Pick the network to switch to. {} will be forgotten.",
+                            current.ssid
+                        ))
+                        .size(13),
+                    );
+                }
+
+                let body: Element<Message> = if !managed {
+                    column![
+                        text(format!(
+                            "{} isn't managed by NetworkManager",
+                            devices[*selected_device].interface
+                        ))
+                        .size(16),
+                        button("Manage").on_press(Message::SetManaged(true)),
+                    ]
+                    .spacing(10)
+                    .height(iced::Fill)
+                    .into()
+                } else if !wifi_enabled {
+                    column![text("WiFi is disabled").size(16)]
+                        .height(iced::Fill)
+                        .into()
+                } else if let Some(draft) = advanced {
+                    let ssid = connecting_ssid.as_deref().unwrap_or_default();
+                    let enterprise = find_network(networks, ssid, *connecting_band)
+                        .is_some_and(|n| draft.key_mgmt_for(n) == nm::KeyMgmt::WpaEap);
+                    let anonymous_identity = enterprise.then(|| {
+                        text_input("Anonymous identity (optional)", &draft.anonymous_identity)
+                            .on_input(Message::AnonymousIdentityChanged)
+                            .size(14)
+                    });
+                    let static_ip4 = draft.ip4.manual.then(|| {
+                        column![
+                            text_input("Address, e.g. 192.168.1.20/24", &draft.ip4.address)
+                                .on_input(Message::Ip4AddressChanged)
+                                .size(14),
+                            text_input("Gateway", &draft.ip4.gateway)
+                                .on_input(Message::Ip4GatewayChanged)
+                                .size(14),
+                        ]
+                        .spacing(10)
+                    });
+                    // Apply is disabled until the address and DNS servers are
+                    // valid
+                    let ip4_check = draft.ip4.check();
+                    let ip4_error = ip4_check
+                        .as_ref()
+                        .err()
+                        .filter(|_| !draft.ip4.address.is_empty() || !draft.ip4.dns.is_empty())
+                        .map(|e| text(e.clone()).size(12).style(text::danger));
+                    column![
+                        text(format!("Advanced settings for {ssid}")).size(16),
+                        row![
+                            text("Key management").size(14).width(iced::Fill),
+                            pick_list(
+                                nm::KeyMgmt::ALL,
+                                Some(draft.key_mgmt),
+                                Message::KeyMgmtSelected,
+                            )
+                            .text_size(14),
+                        ]
+                        .align_y(iced::Alignment::Center)
+                        .spacing(10),
+                        checkbox(draft.interface_name.is_some())
+                            .label(format!(
+                                "Only use on {}",
+                                devices[*selected_device].interface
+                            ))
+                            .on_toggle(Message::PinInterfaceToggled)
+                            .text_size(14),
+                        anonymous_identity,
+                        checkbox(draft.metered)
+                            .label("Metered, e.g. a phone hotspot")
+                            .on_toggle(Message::MeteredToggled)
+                            .text_size(14),
+                        checkbox(draft.ip4.manual)
+                            .label("Static IPv4 address")
+                            .on_toggle(Message::Ip4ManualToggled)
+                            .text_size(14),
+                        static_ip4,
+                        text_input(
+                            "DNS servers, e.g. 1.1.1.1, 8.8.8.8 (default: from DHCP)",
+                            &draft.ip4.dns,
+                        )
+                        .on_input(Message::Ip4DnsChanged)
+                        .size(14),
+                        ip4_error,
+                        row![
+                            iced::widget::space::horizontal(),
+                            button("Cancel").on_press(Message::CancelAdvanced),
+                            button("Apply").on_press_maybe(
+                                ip4_check.is_ok().then_some(Message::ApplyAdvanced)
+                            ),
+                        ]
+                        .spacing(10),
+                    ]
+                    .spacing(10)
+                    .padding(6)
+                    .height(iced::Fill)
+                    .into()
+                } else if networks.is_empty() && scanned_at.is_none() {
+                    skeleton_rows().height(iced::Fill).into()
+                } else if networks.is_empty() {
+                    column![text("Scanning...").size(16)]
+                        .height(iced::Fill)
+                        .into()
+                } else {
+                    let list = self.list_rows(networks).into_iter().enumerate().fold(
+                        column![].spacing(4),
+                        |col, (i, (network, ap_count))| {
+                            let is_entering_password = connecting_ssid.as_deref()
+                                == Some(&network.ssid)
+                                && (!self.show_bands
+                                    || connecting_band.is_none()
+                                    || network.band() == *connecting_band);
+
+                            let chip = if network.is_saved {
+                                "saved"
+                            } else if network.security == "Open" {
+                                "open"
+                            } else {
+                                "password required"
+                            };
+                            let mut ssid_text = row![
+                                text(&network.ssid).size(16),
+                                container(text(chip).size(11))
+                                    .padding([1, 6])
+                                    .style(container::rounded_box),
+                            ]
+                            .align_y(iced::Alignment::Center)
+                            .spacing(8);
+                            if network.metered {
+                                ssid_text = ssid_text.push(
+                                    container(text("metered").size(11))
+                                        .padding([1, 6])
+                                        .style(container::rounded_box),
+                                );
+                            }
+                            if ap_count > 1 {
+                                ssid_text = ssid_text.push(text(format!("×{ap_count}")).size(11));
+                            }
+                            let mut info = network.security.clone();
+                            if let Some(band) = network.band() {
+                                info.push_str(&format!("  ·  {band}"));
+                            }
+                            if let Some(autoconnect) = network.autoconnect {
+                                info.push_str(&format!("  ·  {autoconnect}"));
+                            }
+                            if network.is_primary {
+                                info.push_str("  ·  Primary");
+                            }
+                            if let Some(bssid) = &network.pinned_bssid {
+                                info.push_str(&format!("  ·  pinned to {bssid}"));
+                            }
+                            let info = row![
+                                signal_icon(network.strength, network.security != "Open"),
+                                text(info).size(13),
+                            ]
+                            .align_y(iced::Alignment::Center)
+                            .spacing(6);
+
+                            let network_row = if is_entering_password {
+                                // Password input row
+                                let input = text_input("Password", password)
+                                    .id("password-input")
+                                    .on_input(Message::PasswordChanged)
+                                    .on_submit(Message::SubmitConnect)
+                                    .secure(!*password_visible)
+                                    .size(14)
+                                    .width(iced::Fill);
+
+                                let advanced = button(text("Advanced...").size(14))
+                                    .on_press(Message::EditAdvanced);
+
+                                let mut r = row![]
+                                    .align_y(iced::Alignment::Center)
+                                    .spacing(6)
+                                    .padding(6);
+                                if options.key_mgmt_for(network) == nm::KeyMgmt::WpaEap {
+                                    r = r.push(
+                                        text_input("Username", &options.identity)
+                                            .id("identity-input")
+                                            .on_input(Message::IdentityChanged)
+                                            .on_submit(Message::SubmitConnect)
+                                            .size(14)
+                                            .width(iced::Fill),
+                                    );
+                                }
+                                let eye = button(
+                                    text(if *password_visible { "Hide" } else { "Show" }).size(14),
+                                )
+                                .on_press(Message::TogglePasswordVisibility);
+                                r.push(input)
+                                    .push(eye)
+                                    .push(signal_icon(network.strength, network.security != "Open"))
+                                    .push(advanced)
+                            } else {
+                                let mut details = column![ssid_text, info].spacing(2);
+                                if !self.show_all_aps && !network.bssid.is_empty() {
+                                    details = details.push(text(&network.bssid).size(11));
+                                }
+                                let mut r = row![details, iced::widget::space::horizontal(),]
+                                    .align_y(iced::Alignment::Center)
+                                    .padding(6);
+
+                                if network.is_saved && !*switching {
+                                    if devices.len() > 1 {
+                                        let interface = &devices[*selected_device].interface;
+                                        let preferred = self
+                                            .config
+                                            .preferred_devices
+                                            .get(&network.ssid)
+                                            .is_some_and(|p| p == interface);
+                                        let ssid = network.ssid.clone();
+                                        r = r.push(
+                                            checkbox(preferred)
+                                                .label(format!("Prefer {interface}"))
+                                                .on_toggle(move |prefer| {
+                                                    Message::PreferDevice(ssid.clone(), prefer)
+                                                })
+                                                .text_size(12),
+                                        );
+                                    }
+                                    let ssid = network.ssid_bytes.clone();
+                                    let metered_ssid = network.ssid_bytes.clone();
+                                    r = r
+                                        .push(
+                                            checkbox(
+                                                network.autoconnect.is_some_and(|a| a.enabled),
+                                            )
+                                            .label("Auto")
+                                            .on_toggle(move |enabled| {
+                                                Message::SetAutoconnect(ssid.clone(), enabled)
+                                            })
+                                            .text_size(12),
+                                        )
+                                        .push(
+                                            checkbox(network.metered)
+                                                .label("Metered")
+                                                .on_toggle(move |metered| {
+                                                    Message::SetMetered(
+                                                        metered_ssid.clone(),
+                                                        metered,
+                                                    )
+                                                })
+                                                .text_size(12),
+                                        );
+                                    if network.pinned_bssid.is_some() {
+                                        r =
+                                            r.push(button("Unpin").on_press(Message::Unpin(
+                                                network.ssid_bytes.clone(),
+                                            )));
+                                    }
+                                    r = r
+                                        .push(
+                                            button("IP…").on_press(Message::EditIp(
+                                                network.ssid_bytes.clone(),
+                                            )),
+                                        )
+                                        .push(
+                                            button("Share").on_press(Message::Share(
+                                                network.ssid_bytes.clone(),
+                                            )),
+                                        )
+                                        .push(
+                                            button("Forget").on_press(Message::Forget(
+                                                network.ssid_bytes.clone(),
+                                            )),
+                                        )
+                                        .spacing(6);
+                                }
+                                if network.is_connected && *switching {
+                                    r = r.push(button("Cancel").on_press(Message::CancelSwitch));
+                                } else if network.is_connected {
+                                    r = r
+                                        .push(button("Reassociate").on_press(Message::Reassociate))
+                                        .push(button("Switch…").on_press(Message::StartSwitch))
+                                        .push(button("Disconnect").on_press(Message::Disconnect))
+                                        .spacing(6);
+                                } else if *switching {
+                                    r =
+                                        r.push(button("Switch here").on_press(
+                                            Message::ForgetAndConnect(network.ssid.clone()),
+                                        ));
+                                } else if network.is_saved {
+                                    r = r.push(
+                                        button("Reconnect")
+                                            .on_press(Message::Reconnect(network.ssid.clone())),
+                                    );
+                                } else {
+                                    // An ellipsis when clicking asks for a password first
+                                    let label = if network.connects_instantly() {
+                                        "Connect"
+                                    } else {
+                                        "Connect…"
+                                    };
+                                    let message =
+                                        App::row_action(network, self.show_bands, *switching);
+                                    r = r.push(button(label).on_press(message));
+                                }
+
+                                r
+                            };
+
+                            let is_highlighted = *highlighted == Some(i);
+                            let network_row = container(network_row).style(move |theme: &Theme| {
+                                if is_highlighted {
+                                    container::background(
+                                        theme.extended_palette().background.weak.color,
+                                    )
+                                    .border(iced::border::rounded(4))
+                                } else {
+                                    container::Style::default()
+                                }
+                            });
+
+                            let mut col = col.push(network_row);
+                            if is_entering_password && let Some(error) = password_error {
+                                col = col.push(
+                                    container(text(error).size(12).style(text::danger))
+                                        .padding([0, 6]),
+                                );
+                            }
+                            if network.is_connected && !*switching {
+                                let mut lines = match (ip4, ip6) {
+                                    (Some(ip4), _) => {
+                                        let mut lines =
+                                            vec![format!("IPv4 {}/{}", ip4.address, ip4.prefix)];
+                                        if let Some(gateway) = &ip4.gateway {
+                                            lines.push(format!("Gateway {gateway}"));
+                                        }
+                                        if !ip4.dns.is_empty() {
+                                            lines.push(format!("DNS {}", ip4.dns.join(", ")));
+                                        }
+                                        if let Some(ip6) = ip6 {
+                                            lines.push(format!("IPv6 {ip6}"));
+                                        }
+                                        lines
+                                    }
+                                    (None, Some(ip6)) => vec![format!("IPv6 only: {ip6}")],
+                                    (None, None) => vec!["Obtaining address…".to_string()],
+                                };
+                                if let Some((ssid, since)) = &self.connected_since
+                                    && *ssid == network.ssid
+                                {
+                                    lines.push(format!(
+                                        "Connected for {}",
+                                        format_uptime(since.elapsed())
+                                    ));
+                                }
+                                lines.push(match bitrate {
+                                    0 => "Link speed unknown, not associated".to_string(),
+                                    kbps => format!("Link {}", format_bitrate(*kbps)),
+                                });
+                                col = col.push(
+                                    container(text(lines.join("\n")).size(12)).padding([0, 12]),
+                                );
+                            }
+                            if self.show_all_aps {
+                                // Merged rows stand for the SSID's access
+                                // points on every band
+                                let aps = networks
+                                    .iter()
+                                    .filter(|n| {
+                                        n.ssid == network.ssid
+                                            && (!self.show_bands || n.band() == network.band())
+                                    })
+                                    .flat_map(|n| &n.access_points);
+                                for ap in aps {
+                                    let mut line = format!("{}  {}%", ap.bssid, ap.strength);
+                                    if let Some(band) = nm::band_from_frequency(ap.frequency) {
+                                        line.push_str(&format!("  ·  {band}"));
+                                    }
+                                    if ap.is_connected {
+                                        line.push_str("  ·  connected");
+                                    }
+                                    let mut ap_row = row![text(line).size(12)]
+                                        .align_y(iced::Alignment::Center)
+                                        .padding([0, 24]);
+                                    if !ap.is_connected && !*switching && !ap.bssid.is_empty() {
+                                        let band = network.band().filter(|_| self.show_bands);
+                                        ap_row =
+                                            ap_row.push(iced::widget::space::horizontal()).push(
+                                                button(text("Connect to this AP").size(12))
+                                                    .padding([2, 8])
+                                                    .on_press(Message::ConnectToAp(
+                                                        network.ssid.clone(),
+                                                        band,
+                                                        ap.bssid.clone(),
+                                                    )),
+                                            );
+                                    }
+                                    col = col.push(ap_row);
+                                }
+                            }
+                            col.push(iced::widget::rule::horizontal(1))
+                        },
+                    );
+
+                    let thin_scrollbar = scrollable::Scrollbar::new()
+                        .width(6)
+                        .scroller_width(6)
+                        .spacing(0);
+
+                    scrollable(list)
+                        .direction(scrollable::Direction::Vertical(thin_scrollbar))
+                        .height(iced::Fill)
+                        .into()
+                };
+
+                let device = &devices[*selected_device].interface;
+                let status = match networks.iter().find(|n| n.is_connected) {
+                    Some(network) => {
+                        let mut parts = vec![network.ssid.clone()];
+                        if let Some(ip4) = ip4 {
+                            parts.push(ip4.to_string());
+                        }
+                        match ip4_method.as_deref() {
+                            Some("auto") => parts.push("DHCP".to_string()),
+                            Some("manual") => parts.push("Static".to_string()),
+                            _ => {}
+                        }
+                        // Flag a downgrade, e.g. a WPA2 profile on a network
+                        // offering WPA3
+                        if let Some(security) = connected_security
+                            && network.security == "WPA3"
+                            && security != "WPA3"
+                        {
+                            parts.push(format!("via {security} ({} available)", network.security));
+                        }
+                        parts.push(format!("{}%", network.strength));
+                        match (network.channel(), network.band()) {
+                            (Some(channel), Some(band)) => {
+                                parts.push(format!("channel {channel} ({band})"));
+                            }
+                            (None, Some(band)) => parts.push(band.to_string()),
+                            _ => {}
+                        }
+                        parts.push(device.clone());
+                        parts.join("  ·  ")
+                    }
+                    None if !wifi_enabled => format!("WiFi off  ·  {device}"),
+                    None => match connected_ssid {
+                        Some(ssid) if scanned_at.is_none() => format!("{ssid}  ·  {device}"),
+                        _ => format!("Disconnected  ·  {device}"),
+                    },
+                };
+
+                let probe_status = match &self.probe {
+                    Probe::Idle => String::new(),
+                    Probe::Running => "Testing...".to_string(),
+                    Probe::Done(Ok(latency)) => {
+                        format!("Online ({} ms)", latency.as_millis())
+                    }
+                    Probe::Done(Err(e)) => e.clone(),
+                };
+                let test_button = button(text("Test").size(13)).padding([2, 8]);
+                let test_button = match self.probe {
+                    Probe::Running => test_button,
+                    _ => test_button.on_press(Message::TestConnectivity),
+                };
+                let mut footer = row![text(status).size(13)]
+                    .align_y(iced::Alignment::Center)
+                    .spacing(10);
+                if networks.iter().any(|n| n.is_connected) && self.strength.samples.len() > 1 {
+                    footer = footer.push(text(self.strength.sparkline()).size(13));
+                }
+                let footer = footer
+                    .push(iced::widget::space::horizontal())
+                    .push(text(probe_status).size(13))
+                    .push(test_button)
+                    .push(
+                        button(text("Stats").size(13))
+                            .padding([2, 8])
+                            .on_press(Message::ToggleDiagnostics),
+                    )
+                    .push(
+                        button(text("Active").size(13))
+                            .padding([2, 8])
+                            .on_press(Message::ShowActiveConnections),
+                    )
+                    .push(
+                        button(text("Saved").size(13))
+                            .padding([2, 8])
+                            .on_press(Message::ShowSavedNetworks),
+                    )
+                    .push(
+                        checkbox(self.config.always_on_top)
+                            .label("On top")
+                            .on_toggle(Message::AlwaysOnTop)
+                            .text_size(13),
+                    );
+
+                let mut page = page.push(body).push(iced::widget::rule::horizontal(1));
+
+                if let Some(Throughput {
+                    error: Some(error), ..
+                }) = &self.throughput
+                {
+                    page = page.push(text(error).size(13));
+                } else if let Some(throughput) = &self.throughput {
+                    let max = throughput
+                        .rx_kbps
+                        .iter()
+                        .chain(&throughput.tx_kbps)
+                        .copied()
+                        .max()
+                        .unwrap_or(0);
+                    let line = |label: &str, samples: &std::collections::VecDeque<u64>| {
+                        let rate = samples.back().copied().map(format_rate).unwrap_or_default();
+                        row![
+                            text(format!("{label} {rate}")).size(13).width(110),
+                            text(sparkline(samples.iter().copied(), max)).size(13),
+                        ]
+                        .spacing(10)
+                    };
+                    page = page.push(column![
+                        line("↓", &throughput.rx_kbps),
+                        line("↑", &throughput.tx_kbps)
+                    ]);
+                }
+
+                if self.throughput.is_some() {
+                    let mut channels = column![
+                        text(format!(
+                            "Regulatory domain: {}",
+                            match self.regdom.as_deref() {
+                                Some("00") => "not set",
+                                Some(regdom) => regdom,
+                                None => "unknown",
+                            }
+                        ))
+                        .size(13)
+                    ];
+                    // Without the domain the notes would only be guesses
+                    if let Some(regdom) = &self.regdom {
+                        for (band, note) in regdom::restrictions(regdom) {
+                            channels = channels.push(text(format!("{band}: {note}")).size(13));
+                        }
+                    }
+                    page = page.push(channels);
+                }
+
+                page.push(footer).into()
+            }
+            State::JoinOther {
+                ssid,
+                security,
+                password,
+                error,
+                ..
+            } => {
+                let mut col = column![
+                    text("Join other network").size(22),
+                    text_input("Network name (SSID)", ssid)
+                        .id("join-ssid-input")
+                        .on_input(Message::JoinSsidChanged)
+                        .on_submit(Message::SubmitJoin)
+                        .size(14),
+                    pick_list(
+                        JOIN_SECURITY,
+                        Some(*security),
+                        Message::JoinSecuritySelected
+                    )
+                    .text_size(14),
+                ]
+                .spacing(10);
+
+                if *security != "Open" {
+                    col = col.push(
+                        text_input("Password", password)
+                            .on_input(Message::JoinPasswordChanged)
+                            .on_submit(Message::SubmitJoin)
+                            .secure(true)
+                            .size(14),
+                    );
+                }
+
+                if let Some(error) = error {
+                    col = col.push(text(error).size(13));
+                }
+
+                col.push(
+                    row![
+                        button("Cancel").on_press(Message::CancelConnect),
+                        button("Join").on_press(Message::SubmitJoin),
+                    ]
+                    .spacing(10),
+                )
+                .into()
+            }
+            State::EditIp {
+                ssid,
+                settings,
+                reactivate,
+                error,
+                ..
+            } => {
+                let mut col = column![
+                    text(format!("IP settings for {}", String::from_utf8_lossy(ssid))).size(22)
+                ]
+                .spacing(10);
+
+                if let Some(settings) = settings {
+                    col = col.push(
+                        checkbox(settings.manual)
+                            .label("Static address")
+                            .on_toggle(Message::Ip4ManualToggled)
+                            .text_size(14),
+                    );
+                    if settings.manual {
+                        col = col
+                            .push(
+                                text_input("Address, e.g. 192.168.1.20/24", &settings.address)
+                                    .on_input(Message::Ip4AddressChanged)
+                                    .on_submit(Message::SaveIp4)
+                                    .size(14),
+                            )
+                            .push(
+                                text_input("Gateway", &settings.gateway)
+                                    .on_input(Message::Ip4GatewayChanged)
+                                    .on_submit(Message::SaveIp4)
+                                    .size(14),
+                            );
+                    }
+                    col = col
+                        .push(
+                            text_input("DNS servers (default: from DHCP)", &settings.dns)
+                                .on_input(Message::Ip4DnsChanged)
+                                .on_submit(Message::SaveIp4)
+                                .size(14),
+                        )
+                        .push(
+                            checkbox(*reactivate)
+                                .label("Reconnect to apply")
+                                .on_toggle(Message::Ip4ReactivateToggled)
+                                .text_size(14),
+                        );
+                } else if error.is_none() {
+                    col = col.push(text("Loading...").size(14));
+                }
+
+                if let Some(error) = error {
+                    col = col.push(text(error).size(13));
+                }
+
+                col.push(
+                    row![
+                        button("Cancel").on_press(Message::CancelConnect),
+                        button("Save")
+                            .on_press_maybe(settings.is_some().then_some(Message::SaveIp4)),
+                    ]
+                    .spacing(10),
+                )
+                .into()
+            }
+            State::ActiveConnections {
+                connections, error, ..
+            } => {
+                let mut col = column![text("Active connections").size(22)].spacing(10);
+
+                match connections {
+                    None => col = col.push(text("Loading...").size(14)),
+                    Some(connections) if connections.is_empty() => {
+                        col = col.push(text("No active connections").size(14));
+                    }
+                    Some(connections) => {
+                        for connection in connections {
+                            let mut info = connection.connection_type.clone();
+                            if !connection.interfaces.is_empty() {
+                                info.push_str("  ·  ");
+                                info.push_str(&connection.interfaces.join(", "));
+                            }
+                            col = col.push(
+                                row![
+                                    column![text(&connection.id).size(16), text(info).size(13)]
+                                        .spacing(2),
+                                    iced::widget::space::horizontal(),
+                                    button("Deactivate")
+                                        .on_press(Message::Deactivate(connection.path.clone())),
+                                ]
+                                .align_y(iced::Alignment::Center)
+                                .padding(6),
+                            );
+                        }
+                    }
+                }
+
+                if let Some(error) = error {
+                    col = col.push(text(error).size(13));
+                }
+
+                col.push(button("Back").on_press(Message::CancelConnect))
+                    .into()
+            }
+            State::SavedNetworks {
+                networks, error, ..
+            } => {
+                let mut col = column![text("Saved networks").size(22)].spacing(10);
+
+                match networks {
+                    None => col = col.push(text("Loading...").size(14)),
+                    Some(networks) if networks.is_empty() => {
+                        col = col.push(text("No saved networks").size(14));
+                    }
+                    Some(networks) => {
+                        let list = networks.iter().fold(column![].spacing(4), |list, network| {
+                            let ssid = network.ssid_bytes.clone();
+                            let autoconnect = nm::AutoConnect {
+                                enabled: network.autoconnect,
+                                priority: network.autoconnect_priority,
+                            };
+                            list.push(
+                                row![
+                                    column![
+                                        text(&network.ssid).size(16),
+                                        text(format!("{}  ·  {autoconnect}", network.security))
+                                            .size(13)
+                                    ]
+                                    .spacing(2),
+                                    iced::widget::space::horizontal(),
+                                    checkbox(network.autoconnect)
+                                        .label("Auto")
+                                        .on_toggle(move |enabled| {
+                                            Message::SetAutoconnect(ssid.clone(), enabled)
+                                        })
+                                        .text_size(12),
+                                    button("Share")
+                                        .on_press(Message::Share(network.ssid_bytes.clone())),
+                                    button("Forget")
+                                        .on_press(Message::Forget(network.ssid_bytes.clone())),
+                                ]
+                                .align_y(iced::Alignment::Center)
+                                .spacing(6)
+                                .padding(6),
+                            )
+                        });
+                        col = col.push(scrollable(list).height(iced::Fill));
+                    }
+                }
+
+                if let Some(error) = error {
+                    col = col.push(text(error).size(13));
+                }
+
+                col.push(button("Back").on_press(Message::CancelConnect))
+                    .into()
+            }
+            State::Error {
+                message,
+                devices,
+                retry,
+                ..
+            } => {
+                let title = match retry {
+                    Some(Retry::Connect(request)) => {
+                        format!("Couldn't connect to {}", request.network.ssid)
+                    }
+                    Some(Retry::Disconnect(Some(ssid))) => {
+                        format!("Couldn't disconnect from {ssid}")
+                    }
+                    Some(Retry::Disconnect(None)) => "Couldn't disconnect".to_string(),
+                    None => "Error".to_string(),
+                };
+                let mut col = column![text(title).size(22), text(message).size(14),].spacing(10);
+
+                let mut buttons = row![].spacing(10);
+                if devices.is_some() {
+                    buttons = buttons.push(button("Back").on_press(Message::Back));
+                    if retry.is_some() {
+                        buttons = buttons.push(button("Retry").on_press(Message::Retry));
+                    }
+                }
+                buttons = buttons
+                    .push(button("Reload").on_press(Message::Reload))
+                    .push(button("Copy diagnostics").on_press(Message::CopyDiagnostics));
+                col = col.push(buttons);
+
+                col.into()
+            }
+        };
+
+        let content = container(content).padding(20).width(iced::Fill);
+        match &self.share {
+            Some(share) => modal(content, share_dialog(share), Message::CloseShare),
+            None => content.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use backend::Backend;
+    use iced::futures::future::BoxFuture;
+    use iced::futures::stream::BoxStream;
+
+    /// Canned NM state. Connection attempts fail with `connect_error`.
+    struct Fake {
+        devices: Vec<nm::WifiDevice>,
+        networks: Vec<nm::Network>,
+        connect_error: String,
+    }
+
+    impl backend::Backend for Fake {
+        fn list_wifi_devices(&self) -> BoxFuture<'static, Result<Vec<nm::WifiDevice>, String>> {
+            Box::pin(std::future::ready(Ok(self.devices.clone())))
+        }
+
+        fn scan_networks(&self, _device_path: String) -> BoxStream<'static, nm::ScanProgress> {
+            let scan = nm::Scan {
+                networks: self.networks.clone(),
+                scan_denied: false,
+                ip4: None,
+                ip6: None,
+                bitrate: 0,
+                ip4_method: None,
+                connected_security: None,
+                last_scan_age: None,
+            };
+            Box::pin(iced::futures::stream::iter([
+                nm::ScanProgress::Partial(self.networks[..self.networks.len() / 2].to_vec()),
+                nm::ScanProgress::Done(Ok(scan)),
+            ]))
+        }
+
+        fn connect(
+            &self,
+            _network: nm::Network,
+            _password: String,
+            _options: nm::ConnectOptions,
+            _on_phase: Box<dyn FnMut(nm::ConnectPhase) + Send>,
+        ) -> BoxFuture<'static, Result<(), nm::ConnectError>> {
+            let error = nm::ConnectError::Other(self.connect_error.clone());
+            Box::pin(std::future::ready(Err(error)))
+        }
+
+        fn reconnect(
+            &self,
+            _network: nm::Network,
+            _on_phase: Box<dyn FnMut(nm::ConnectPhase) + Send>,
+        ) -> BoxFuture<'static, Result<(), nm::ConnectError>> {
+            let error = nm::ConnectError::Other(self.connect_error.clone());
+            Box::pin(std::future::ready(Err(error)))
+        }
+
+        fn disconnect(
+            &self,
+            _device_path: String,
+            _ssid: Option<String>,
+        ) -> BoxFuture<'static, Result<(), String>> {
+            Box::pin(std::future::ready(Ok(())))
+        }
+
+        fn get_wifi_enabled(&self) -> BoxFuture<'static, Result<bool, String>> {
+            Box::pin(std::future::ready(Ok(true)))
+        }
+
+        fn set_wifi_enabled(&self, enabled: bool) -> BoxFuture<'static, Result<bool, String>> {
+            Box::pin(std::future::ready(Ok(enabled)))
+        }
+    }
+
+    fn device(name: &str) -> nm::WifiDevice {
+        nm::WifiDevice {
+            path: format!("/org/freedesktop/NetworkManager/Devices/{name}"),
+            interface: name.to_string(),
+            is_default_route: false,
+            managed: true,
+        }
+    }
+
+    fn network(ssid: &str, security: &str) -> nm::Network {
+        nm::Network {
+            ssid: ssid.to_string(),
+            ssid_bytes: ssid.as_bytes().to_vec(),
+            strength: 70,
+            security: security.to_string(),
+            is_connected: false,
+            is_primary: false,
+            is_saved: false,
+            autoconnect: None,
+            metered: false,
+            pinned_bssid: None,
+            ap_count: 1,
+            frequency: 2412,
+            bssid: String::new(),
+            access_points: Vec::new(),
+            ap_path: format!("/org/freedesktop/NetworkManager/AccessPoint/{ssid}"),
+            device_path: String::new(),
+        }
+    }
+
+    fn app(fake: Fake) -> (App, Arc<Fake>) {
+        let fake = Arc::new(fake);
+        let (app, _) = App::with_backend(config::Config::default(), fake.clone());
+        (app, fake)
+    }
+
+    /// Feed the fake's scan results for a device, as `scan_task` would.
+    fn scan(app: &mut App, fake: &Fake, device_path: String) {
+        let progress: Vec<_> =
+            async_io::block_on(fake.scan_networks(device_path.clone()).collect());
+        for progress in progress {
+            let _ = app.update(match progress {
+                nm::ScanProgress::Partial(networks) => {
+                    Message::NetworksPartial(networks, device_path.clone())
+                }
+                nm::ScanProgress::Done(result) => {
+                    Message::NetworksLoaded(result, device_path.clone())
+                }
+            });
+        }
+    }
+
+    /// Feed the fake's device list and scan results, as the tasks `App::with_backend`
+    /// and `DevicesLoaded` start would.
+    fn load(app: &mut App, fake: &Fake) {
+        let _ = app.update(Message::DevicesLoaded(async_io::block_on(
+            fake.list_wifi_devices(),
+        )));
+        let (devices, selected) = app.state.device_info().unwrap();
+        scan(app, fake, devices[selected].path.clone());
+    }
+
+    #[test]
+    fn failed_connect_goes_back_to_a_clean_list() {
+        let (mut app, fake) = app(Fake {
+            devices: vec![device("wlan0"), device("wlan1")],
+            networks: vec![network("cafe", "WPA2"), network("library", "Open")],
+            connect_error: "Secrets were required, but not provided".to_string(),
+        });
+        load(&mut app, &fake);
+        let _ = app.update(Message::DeviceSelected(device("wlan1")));
+        scan(&mut app, &fake, device("wlan1").path);
+        let State::Loaded {
+            networks,
+            selected_device: 1,
+            ..
+        } = &app.state
+        else {
+            panic!("expected the list for wlan1");
+        };
+        assert_eq!(networks.len(), 2);
+
+        // A secured network asks for a password first
+        let _ = app.update(Message::Connect("cafe".to_string()));
+        let _ = app.update(Message::PasswordChanged("hunter22".to_string()));
+        let State::Loaded {
+            connecting_ssid: Some(ssid),
+            password,
+            ..
+        } = &app.state
+        else {
+            panic!("expected the password entry");
+        };
+        assert_eq!(ssid, "cafe");
+        assert_eq!(password, "hunter22");
+
+        let _ = app.update(Message::SubmitConnect);
+        let State::Connecting {
+            selected_device: 1,
+            request,
+            ..
+        } = &app.state
+        else {
+            panic!("expected a connection attempt on wlan1");
+        };
+        assert_eq!(request.network.ssid, "cafe");
+        assert_eq!(request.password, "hunter22");
+        assert!(!request.reconnect);
+
+        let result = async_io::block_on(fake.connect(
+            request.network.clone(),
+            request.password.clone(),
+            request.options.clone(),
+            Box::new(|_| {}),
+        ));
+        let _ = app.update(Message::Connected(result));
+        let State::Error {
+            message,
+            devices: Some(_),
+            selected_device: 1,
+            retry: Some(Retry::Connect(_)),
+        } = &app.state
+        else {
+            panic!("expected the error screen");
+        };
+        assert_eq!(message, "Secrets were required, but not provided");
+
+        let _ = app.update(Message::Back);
+        let State::Loaded {
+            selected_device: 1,
+            connecting_ssid: None,
+            password,
+            ..
+        } = &app.state
+        else {
+            panic!("expected the list for wlan1");
+        };
+        assert!(password.is_empty());
+    }
+
+    #[test]
+    fn open_network_connects_without_password() {
+        let (mut app, fake) = app(Fake {
+            devices: vec![device("wlan0")],
+            networks: vec![network("cafe", "WPA2"), network("library", "Open")],
+            connect_error: String::new(),
+        });
+        load(&mut app, &fake);
+
+        let _ = app.update(Message::Connect("library".to_string()));
+        let State::Connecting { request, .. } = &app.state else {
+            panic!("expected a connection attempt");
+        };
+        assert_eq!(request.network.ssid, "library");
+        assert!(request.password.is_empty());
+
+        // Escape tears down the attempt
+        let _ = app.update(Message::CancelConnect);
+        assert!(matches!(app.state, State::Disconnecting { .. }));
+        let _ = app.update(Message::ConnectCancelled);
+        assert!(matches!(app.state, State::Loaded { .. }));
+    }
+
+    #[test]
+    fn retrying_a_failed_connect() {
+        let (mut app, fake) = app(Fake {
+            devices: vec![device("wlan0")],
+            networks: vec![network("cafe", "WPA2"), network("library", "Open")],
+            connect_error: "Connection timed out".to_string(),
+        });
+        load(&mut app, &fake);
+
+        let _ = app.update(Message::Connect("library".to_string()));
+        let _ = app.update(Message::Connected(Err(nm::ConnectError::Other(
+            "Connection timed out".to_string(),
+        ))));
+        let _ = app.update(Message::Retry);
+        let State::Connecting { request, .. } = &app.state else {
+            panic!("expected a new connection attempt");
+        };
+        assert_eq!(request.network.ssid, "library");
+
+        // A wrong password goes back to the password entry, with the
+        // password to correct
+        let _ = app.update(Message::CancelConnect);
+        let _ = app.update(Message::ConnectCancelled);
+        scan(&mut app, &fake, device("wlan0").path);
+        let _ = app.update(Message::Connect("cafe".to_string()));
+        let _ = app.update(Message::PasswordChanged("hunter21".to_string()));
+        let _ = app.update(Message::SubmitConnect);
+        let _ = app.update(Message::Connected(Err(nm::ConnectError::PasswordRejected)));
+        let State::Loaded {
+            connecting_ssid: Some(ssid),
+            password,
+            options,
+            note: Some(_),
+            ..
+        } = &app.state
+        else {
+            panic!("expected the password entry");
+        };
+        assert_eq!(ssid, "cafe");
+        assert_eq!(password, "hunter21");
+        assert!(!options.replace_saved);
+    }
+
+    #[test]
+    fn arrow_keys_pick_a_network() {
+        let (mut app, fake) = app(Fake {
+            devices: vec![device("wlan0")],
+            networks: vec![network("cafe", "WPA2"), network("library", "Open")],
+            connect_error: String::new(),
+        });
+        load(&mut app, &fake);
+        let highlighted = |app: &App| match &app.state {
+            State::Loaded { highlighted, .. } => *highlighted,
+            _ => panic!("expected the list"),
+        };
+
+        // Stops at the ends
+        let _ = app.update(Message::HighlightUp);
+        assert_eq!(highlighted(&app), Some(1));
+        let _ = app.update(Message::HighlightDown);
+        assert_eq!(highlighted(&app), Some(1));
+        let _ = app.update(Message::HighlightUp);
+        let _ = app.update(Message::HighlightUp);
+        assert_eq!(highlighted(&app), Some(0));
+
+        // A secured network asks for the password, the keys then belong to
+        // the password entry
+        let _ = app.update(Message::ActivateHighlighted);
+        let State::Loaded {
+            connecting_ssid: Some(ssid),
+            ..
+        } = &app.state
+        else {
+            panic!("expected the password entry");
+        };
+        assert_eq!(ssid, "cafe");
+        let _ = app.update(Message::HighlightDown);
+        assert_eq!(highlighted(&app), Some(0));
+
+        let _ = app.update(Message::CancelConnect);
+        let _ = app.update(Message::HighlightDown);
+        let _ = app.update(Message::ActivateHighlighted);
+        let State::Connecting { request, .. } = &app.state else {
+            panic!("expected a connection attempt");
+        };
+        assert_eq!(request.network.ssid, "library");
+    }
+
+    #[test]
+    fn preferred_device_drops_the_other_devices_access_points() {
+        let mut library = network("library", "Open");
+        library.bssid = "00:11:22:33:44:55".to_string();
+        library.access_points = vec![nm::AccessPoint {
+            path: library.ap_path.clone(),
+            bssid: library.bssid.clone(),
+            strength: 70,
+            frequency: 2412,
+            is_connected: false,
+        }];
+        let (mut app, fake) = app(Fake {
+            devices: vec![device("wlan0"), device("wlan1")],
+            networks: vec![library],
+            connect_error: String::new(),
+        });
+        app.config
+            .preferred_devices
+            .insert("library".to_string(), "wlan1".to_string());
+        load(&mut app, &fake);
+
+        let _ = app.update(Message::Connect("library".to_string()));
+        let State::Connecting {
+            selected_device: 1,
+            request,
+            ..
+        } = &app.state
+        else {
+            panic!("expected a connection attempt on wlan1");
+        };
+        assert_eq!(request.network.device_path, device("wlan1").path);
+        assert_eq!(request.network.ap_path, "/");
+        assert!(request.network.bssid.is_empty());
+        assert!(request.network.access_points.is_empty());
+        // It was seen in a scan, just not by wlan1
+        assert!(!request.options.hidden);
+    }
+
+    #[test]
+    fn connected_network_is_never_filtered_out() {
+        let mut connected = network("home", "WPA2");
+        connected.is_connected = true;
+        connected.strength = 20;
+        connected.frequency = 5180;
+        let mut other_band = network("home", "WPA2");
+        other_band.strength = 90;
+        let mut weak = network("neighbour", "WPA2");
+        weak.strength = 30;
+        let (mut app, fake) = app(Fake {
+            devices: vec![device("wlan0")],
+            // Not in the connected-first order a scan gives
+            networks: vec![network("cafe", "WPA2"), other_band, connected, weak],
+            connect_error: String::new(),
+        });
+        app.config.min_strength = 50;
+        load(&mut app, &fake);
+        let State::Loaded { networks, .. } = &app.state else {
+            panic!("expected the list");
+        };
+
+        for show_bands in [false, true] {
+            app.show_bands = show_bands;
+            let rows: Vec<_> = app
+                .list_rows(networks)
+                .into_iter()
+                .map(|(n, _)| (n.ssid.as_str(), n.is_connected))
+                .collect();
+            let expected = if show_bands {
+                [("home", true), ("cafe", false), ("home", false)].as_slice()
+            } else {
+                [("home", true), ("cafe", false)].as_slice()
+            };
+            assert_eq!(rows, expected, "show_bands: {show_bands}");
+        }
+    }
+
+    #[test]
+    fn reloading_devices_keeps_the_selection() {
+        let (mut app, fake) = app(Fake {
+            devices: vec![device("wlan0"), device("wlan1")],
+            networks: Vec::new(),
+            connect_error: String::new(),
+        });
+        load(&mut app, &fake);
+        let _ = app.update(Message::DeviceSelected(device("wlan1")));
+
+        // wlan1 is now listed first
+        let _ = app.update(Message::DevicesLoaded(Ok(vec![
+            device("wlan1"),
+            device("wlan0"),
+        ])));
+        let State::Loaded {
+            devices,
+            selected_device,
+            ..
+        } = &app.state
+        else {
+            panic!("expected the network list");
+        };
+        assert_eq!(devices[*selected_device].interface, "wlan1");
+
+        // Unless it's gone
+        let _ = app.update(Message::DevicesLoaded(Ok(vec![device("wlan0")])));
+        let State::Loaded {
+            selected_device: 0, ..
+        } = &app.state
+        else {
+            panic!("expected wlan0 to be selected");
+        };
+    }
+
+    #[test]
+    fn connection_uptime_resets_when_disconnected() {
+        let (mut app, fake) = app(Fake {
+            devices: vec![device("wlan0")],
+            networks: vec![network("library", "Open")],
+            connect_error: String::new(),
+        });
+        load(&mut app, &fake);
+
+        let _ = app.update(Message::Connect("library".to_string()));
+        let _ = app.update(Message::Connected(Ok(())));
+        assert!(matches!(&app.connected_since, Some((ssid, _)) if ssid == "library"));
+
+        // The rescan after connecting shows it isn't connected anymore
+        let (devices, selected) = app.state.device_info().unwrap();
+        scan(&mut app, &fake, devices[selected].path.clone());
+        assert!(app.connected_since.is_none());
+
+        assert_eq!(format_uptime(std::time::Duration::from_secs(42)), "42s");
+        assert_eq!(
+            format_uptime(std::time::Duration::from_secs(750)),
+            "12m 30s"
+        );
+        assert_eq!(format_uptime(std::time::Duration::from_secs(7380)), "2h 3m");
+    }
 
-        container(content).padding(20).width(iced::Fill).into()
+    #[test]
+    fn signal_bar_counts() {
+        let bars: Vec<usize> = [0, 25, 26, 50, 51, 75, 76, 100]
+            .into_iter()
+            .map(signal_bars)
+            .collect();
+        assert_eq!(bars, [1, 1, 2, 2, 3, 3, 4, 4]);
     }
 }
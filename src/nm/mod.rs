@@ -3,19 +3,72 @@ pub mod proxy;
 use std::collections::HashMap;
 
 use proxy::{
-    AccessPointProxy, ActiveConnectionProxy, DeviceProxy, NetworkManagerProxy,
-    SettingsConnectionProxy, SettingsProxy, WirelessProxy,
+    AccessPointProxy, ActiveConnectionProxy, DeviceProxy, Ip4ConfigProxy, Ip6ConfigProxy,
+    NetworkManagerProxy, SettingsConnectionProxy, SettingsProxy, StatisticsProxy, WirelessProxy,
 };
 
+/// Environment variable naming a D-Bus address to use instead of the system
+/// bus, e.g. a private bus running a mock NetworkManager.
+pub const BUS_ADDRESS_VAR: &str = "NETMAN_DBUS_ADDRESS";
+
+/// The connection `bus` hands out, kept for the next call.
+static BUS: std::sync::Mutex<Option<zbus::Connection>> = std::sync::Mutex::new(None);
+
+/// Connection to the bus NetworkManager is on: the system bus, unless
+/// `BUS_ADDRESS_VAR` is set. The connection is shared between calls, and
+/// replaced when it stops responding, e.g. after a bus restart.
+pub async fn bus() -> zbus::Result<zbus::Connection> {
+    let cached = BUS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(connection) = cached {
+        let ping = connection
+            .call_method(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                Some("org.freedesktop.DBus.Peer"),
+                "Ping",
+                &(),
+            )
+            .await;
+        if ping.is_ok() {
+            return Ok(connection);
+        }
+    }
+
+    let connection = connect_bus().await?;
+    *BUS.lock().unwrap_or_else(|e| e.into_inner()) = Some(connection.clone());
+    Ok(connection)
+}
+
+async fn connect_bus() -> zbus::Result<zbus::Connection> {
+    match std::env::var(BUS_ADDRESS_VAR) {
+        Ok(address) if !address.is_empty() => {
+            zbus::connection::Builder::address(address.as_str())?
+                .build()
+                .await
+        }
+        _ => zbus::Connection::system().await,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct WifiDevice {
     pub path: String,
     pub interface: String,
+    /// The device carries NM's primary connection, i.e. the default route.
+    pub is_default_route: bool,
+    /// NM manages the device. Unmanaged devices are left to other tools.
+    pub managed: bool,
 }
 
 impl std::fmt::Display for WifiDevice {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.interface)
+        write!(f, "{}", self.interface)?;
+        if !self.managed {
+            write!(f, " (unmanaged)")?;
+        } else if self.is_default_route {
+            write!(f, " (default route)")?;
+        }
+        Ok(())
     }
 }
 
@@ -25,17 +78,275 @@ impl PartialEq for WifiDevice {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct Network {
+    /// For display. Not valid UTF-8 SSIDs have their invalid bytes replaced.
     pub ssid: String,
+    /// The SSID as broadcast, used to match saved profiles and create new
+    /// ones.
+    pub ssid_bytes: Vec<u8>,
     pub strength: u8,
     pub security: String,
     pub is_connected: bool,
+    /// The network's connection is NM's primary connection, i.e. it holds the
+    /// default route. Only ever set on the connected network.
+    pub is_primary: bool,
     pub is_saved: bool,
+    /// Auto-connect settings of the saved profile NM would use, if saved.
+    pub autoconnect: Option<AutoConnect>,
+    /// The saved profile is marked metered.
+    pub metered: bool,
+    /// BSSID the saved profile is locked to.
+    pub pinned_bssid: Option<String>,
+    /// Number of access points advertising the SSID. More than one for mesh
+    /// and other multi-AP networks.
+    pub ap_count: usize,
+    /// Frequency of the access point in MHz, 0 if unknown.
+    pub frequency: u32,
+    /// BSSID of the access point, empty if unknown.
+    pub bssid: String,
+    /// All access points advertising the SSID on the band, this one first.
+    /// Set when deduplicating a scan, see `dedup_networks`.
+    pub access_points: Vec<AccessPoint>,
     pub ap_path: String,
     pub device_path: String,
 }
 
+/// An access point behind a network row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AccessPoint {
+    pub path: String,
+    pub bssid: String,
+    pub strength: u8,
+    pub frequency: u32,
+    pub is_connected: bool,
+}
+
+/// `connection.autoconnect` and `connection.autoconnect-priority` of a
+/// profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AutoConnect {
+    pub enabled: bool,
+    pub priority: i32,
+}
+
+impl std::fmt::Display for AutoConnect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let on = if self.enabled { "on" } else { "off" };
+        write!(f, "auto: {on} (prio {})", self.priority)
+    }
+}
+
+/// WiFi band of a frequency in MHz, e.g. "5 GHz".
+pub fn band_from_frequency(frequency: u32) -> Option<&'static str> {
+    match frequency {
+        2400..2500 => Some("2.4 GHz"),
+        4900..5925 => Some("5 GHz"),
+        // WiFi 6E
+        5925..=7125 => Some("6 GHz"),
+        _ => None,
+    }
+}
+
+/// WiFi channel number of a frequency in MHz.
+pub fn channel_from_frequency(frequency: u32) -> Option<u32> {
+    match frequency {
+        // Channel 14 is off the 5 MHz grid
+        2484 => Some(14),
+        2412..2484 => Some((frequency - 2407) / 5),
+        // Japan's 4.9 GHz channels
+        4900..5000 => Some((frequency - 4000) / 5),
+        5000..5925 => Some((frequency - 5000) / 5),
+        5935 => Some(2),
+        5950..=7125 => Some((frequency - 5950) / 5),
+        _ => None,
+    }
+}
+
+impl Network {
+    pub fn band(&self) -> Option<&'static str> {
+        band_from_frequency(self.frequency)
+    }
+
+    /// For the connected network this is the channel of the access point
+    /// the device is associated with.
+    pub fn channel(&self) -> Option<u32> {
+        channel_from_frequency(self.frequency)
+    }
+
+    /// Connecting needs no password: the network is open or has a saved
+    /// profile.
+    pub fn connects_instantly(&self) -> bool {
+        self.security == "Open" || self.is_saved
+    }
+}
+
+/// Result of scanning a device for networks.
+#[derive(Debug, Clone)]
+pub struct Scan {
+    /// One entry per SSID and band, sorted connected first, then by strength.
+    pub networks: Vec<Network>,
+    /// NetworkManager refused to start a new scan because the user lacks the
+    /// `org.freedesktop.NetworkManager.wifi.scan` permission. `networks` then
+    /// only contains the access points NM already knew about.
+    pub scan_denied: bool,
+    /// IPv4 configuration of the device, if it has one.
+    pub ip4: Option<Ip4Info>,
+    /// Global IPv6 address of the device, if it has one.
+    pub ip6: Option<String>,
+    /// Bit rate of the device in kb/s, 0 when not associated.
+    pub bitrate: u32,
+    /// `ipv4.method` of the profile active on the device, e.g. "auto" (DHCP)
+    /// or "manual" (static).
+    pub ip4_method: Option<String>,
+    /// Security of the active profile, e.g. "WPA2". Can be weaker than what
+    /// the connected network offers.
+    pub connected_security: Option<String>,
+    /// Time since NM last finished a scan on the device.
+    pub last_scan_age: Option<std::time::Duration>,
+}
+
+/// Progress of `scan_networks`.
+#[derive(Debug, Clone)]
+pub enum ScanProgress {
+    /// The networks found among the access points processed so far.
+    Partial(Vec<Network>),
+    Done(Result<Scan, String>),
+}
+
+/// Number of access points processed between partial scan results.
+const SCAN_BATCH: usize = 20;
+
+/// Time since boot, on the clock NM's `LastScan` uses (`CLOCK_BOOTTIME`, which
+/// keeps counting during suspend like `/proc/uptime`).
+fn boot_time() -> Option<std::time::Duration> {
+    let uptime = std::fs::read_to_string("/proc/uptime").ok()?;
+    let secs: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+    Some(std::time::Duration::from_secs_f64(secs))
+}
+
+/// Deduplicate by SSID and band: prefer connected, then strongest signal.
+fn dedup_networks(networks: &mut Vec<Network>) {
+    networks.sort_by(|a, b| {
+        b.is_connected
+            .cmp(&a.is_connected)
+            .then(b.strength.cmp(&a.strength))
+    });
+    let mut access_points: HashMap<(Vec<u8>, Option<&str>), Vec<AccessPoint>> = HashMap::new();
+    for network in networks.iter() {
+        access_points
+            .entry((network.ssid_bytes.clone(), network.band()))
+            .or_default()
+            .push(AccessPoint {
+                path: network.ap_path.clone(),
+                bssid: network.bssid.clone(),
+                strength: network.strength,
+                frequency: network.frequency,
+                is_connected: network.is_connected,
+            });
+    }
+    let mut seen = std::collections::HashSet::new();
+    networks.retain(|n| seen.insert((n.ssid_bytes.clone(), n.band())));
+    for network in networks {
+        let aps = access_points
+            .remove(&(network.ssid_bytes.clone(), network.band()))
+            .unwrap_or_default();
+        network.ap_count = aps.len();
+        network.access_points = aps;
+    }
+}
+
+/// Whether a D-Bus error means the caller isn't authorized (polkit denial).
+fn is_permission_error(e: &zbus::Error) -> bool {
+    match e {
+        zbus::Error::MethodError(name, _, _) => matches!(
+            name.as_str(),
+            "org.freedesktop.NetworkManager.PermissionDenied"
+                | "org.freedesktop.DBus.Error.AccessDenied"
+        ),
+        zbus::Error::FDO(e) => matches!(**e, zbus::fdo::Error::AccessDenied(_)),
+        _ => false,
+    }
+}
+
+/// Describe a failed connection request. NM's error names are more telling
+/// than their messages, so common ones get an explanation.
+fn connect_error_message(e: &zbus::Error) -> String {
+    let explanation = match e {
+        _ if is_permission_error(e) => Some("you're not allowed to manage network connections"),
+        zbus::Error::MethodError(name, _, _) => {
+            match name.as_str().rsplit('.').next().unwrap_or_default() {
+                "InvalidSetting" | "InvalidProperty" | "MissingSetting" | "MissingProperty" => {
+                    Some("the connection settings are invalid or incomplete")
+                }
+                "MissingPlugin" => Some(
+                    "a NetworkManager plugin this network needs isn't installed \
+                     (e.g. for enterprise authentication)",
+                ),
+                "ConnectionNotAvailable" => {
+                    Some("the network isn't available on this device right now")
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    match explanation {
+        Some(explanation) => format!("Failed to connect: {explanation} ({e})"),
+        None => format!("Failed to connect: {e}"),
+    }
+}
+
+/// Key management to use when creating a new connection profile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum KeyMgmt {
+    /// Derive from the security type detected from the AP flags.
+    #[default]
+    Auto,
+    WpaPsk,
+    Sae,
+    WpaEap,
+    /// Static WEP key or passphrase.
+    Wep,
+    /// No security section at all (open network).
+    None,
+}
+
+impl KeyMgmt {
+    pub const ALL: [KeyMgmt; 6] = [
+        KeyMgmt::Auto,
+        KeyMgmt::WpaPsk,
+        KeyMgmt::Sae,
+        KeyMgmt::WpaEap,
+        KeyMgmt::Wep,
+        KeyMgmt::None,
+    ];
+
+    /// The key management `connect` picks for a detected security type.
+    pub fn for_security(security: &str) -> KeyMgmt {
+        match security {
+            "Open" => KeyMgmt::None,
+            "WEP" => KeyMgmt::Wep,
+            "WPA3" => KeyMgmt::Sae,
+            "Enterprise" => KeyMgmt::WpaEap,
+            _ => KeyMgmt::WpaPsk,
+        }
+    }
+}
+
+impl std::fmt::Display for KeyMgmt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            KeyMgmt::Auto => "Auto",
+            KeyMgmt::WpaPsk => "wpa-psk",
+            KeyMgmt::Sae => "sae",
+            KeyMgmt::WpaEap => "wpa-eap",
+            KeyMgmt::Wep => "wep",
+            KeyMgmt::None => "none",
+        })
+    }
+}
+
 fn security_from_flags(flags: u32, wpa_flags: u32, rsn_flags: u32) -> String {
     // NM_802_11_AP_FLAGS_PRIVACY = 0x1
     let privacy = flags & 0x1 != 0;
@@ -69,6 +380,14 @@ fn security_from_flags(flags: u32, wpa_flags: u32, rsn_flags: u32) -> String {
 fn get_wifi_ssid(
     s: &HashMap<String, HashMap<String, zbus::zvariant::OwnedValue>>,
 ) -> Option<String> {
+    let ssid_bytes = get_wifi_ssid_bytes(s)?;
+    Some(String::from_utf8_lossy(&ssid_bytes).to_string())
+}
+
+/// The SSID of a WiFi profile as stored, `None` for other profiles.
+fn get_wifi_ssid_bytes(
+    s: &HashMap<String, HashMap<String, zbus::zvariant::OwnedValue>>,
+) -> Option<Vec<u8>> {
     let conn_type = s.get("connection")?.get("type")?;
     let conn_type: &str = conn_type.try_into().ok()?;
     if conn_type != "802-11-wireless" {
@@ -80,41 +399,280 @@ fn get_wifi_ssid(
         .iter()
         .filter_map(|v| u8::try_from(v).ok())
         .collect();
-    let ssid = String::from_utf8_lossy(&ssid_bytes).to_string();
-    if ssid.is_empty() { None } else { Some(ssid) }
+    if ssid_bytes.is_empty() {
+        None
+    } else {
+        Some(ssid_bytes)
+    }
+}
+
+/// IPv4 configuration of a connected device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ip4Info {
+    /// First address of the device, without prefix.
+    pub address: String,
+    /// CIDR prefix length of `address`.
+    pub prefix: u32,
+    /// Default gateway. `None` for link-local or otherwise routerless
+    /// configurations.
+    pub gateway: Option<String>,
+    /// DNS servers.
+    pub dns: Vec<String>,
+}
+
+impl std::fmt::Display for Ip4Info {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)?;
+        match &self.gateway {
+            Some(gateway) => write!(f, " via {gateway}"),
+            None => write!(f, " (no gateway)"),
+        }
+    }
+}
+
+/// Read the first IPv4 address and the gateway configured on a device.
+async fn device_ip4(connection: &zbus::Connection, device_path: &str) -> Option<Ip4Info> {
+    let device = DeviceProxy::builder(connection)
+        .path(device_path)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    let config_path = device.ip4_config().await.ok()?;
+    // "/" means the device has no IPv4 configuration (yet)
+    if config_path.as_str() == "/" {
+        return None;
+    }
+    let config = Ip4ConfigProxy::builder(connection)
+        .path(config_path)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    let addresses = config.address_data().await.ok()?;
+    let first = addresses.first()?;
+    let address: &str = first.get("address")?.try_into().ok()?;
+    let prefix: u32 = first
+        .get("prefix")
+        .and_then(|p| p.try_into().ok())
+        .unwrap_or(32);
+    // Empty when there's no default route through this device
+    let gateway = config.gateway().await.ok().filter(|g| !g.is_empty());
+    let dns = config
+        .nameserver_data()
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|server| {
+            let address: &str = server.get("address")?.try_into().ok()?;
+            Some(address.to_string())
+        })
+        .collect();
+    Some(Ip4Info {
+        address: address.to_string(),
+        prefix,
+        gateway,
+        dns,
+    })
+}
+
+/// The first IPv6 address of a device that isn't link-local.
+async fn device_ip6(connection: &zbus::Connection, device_path: &str) -> Option<String> {
+    let device = DeviceProxy::builder(connection)
+        .path(device_path)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    let config_path = device.ip6_config().await.ok()?;
+    if config_path.as_str() == "/" {
+        return None;
+    }
+    let config = Ip6ConfigProxy::builder(connection)
+        .path(config_path)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    config
+        .address_data()
+        .await
+        .ok()?
+        .iter()
+        .filter_map(|data| {
+            let address: &str = data.get("address")?.try_into().ok()?;
+            Some(address.to_string())
+        })
+        .find(|address| !address.starts_with("fe80:"))
+}
+
+/// IPv4 configuration of a device, if it has one.
+pub async fn ip4_info(device_path: &str) -> Option<Ip4Info> {
+    let connection = bus().await.ok()?;
+    device_ip4(&connection, device_path).await
+}
+
+/// Settings of the profile active on a device.
+async fn active_profile_settings(
+    connection: &zbus::Connection,
+    device_path: &str,
+) -> Option<proxy::ConnectionSettings> {
+    let device = DeviceProxy::builder(connection)
+        .path(device_path)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    let active_path = device.active_connection().await.ok()?;
+    if active_path.as_str() == "/" {
+        return None;
+    }
+    let active = ActiveConnectionProxy::builder(connection)
+        .path(active_path)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    let profile = SettingsConnectionProxy::builder(connection)
+        .path(active.connection().await.ok()?)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    profile.get_settings().await.ok()
+}
+
+/// `ipv4.method` of a profile.
+fn ip4_method(settings: &proxy::ConnectionSettings) -> Option<String> {
+    let method: &str = settings.get("ipv4")?.get("method")?.try_into().ok()?;
+    Some(method.to_string())
+}
+
+/// Security a WiFi profile connects with, named like `security_from_flags`
+/// names what an AP offers.
+fn profile_security(settings: &proxy::ConnectionSettings) -> String {
+    let key_mgmt = settings.get("802-11-wireless-security").map(|security| {
+        security
+            .get("key-mgmt")
+            .and_then(|v| v.try_into().ok())
+            .unwrap_or_default()
+    });
+    security_name(key_mgmt).to_string()
 }
 
-async fn saved_wifi_ssids(connection: &zbus::Connection) -> std::collections::HashSet<String> {
-    let mut ssids = std::collections::HashSet::new();
+/// The security type named by a profile's `key-mgmt`, `None` when the profile
+/// has no security section.
+fn security_name(key_mgmt: Option<&str>) -> &str {
+    match key_mgmt {
+        None => "Open",
+        Some("sae") => "WPA3",
+        Some("wpa-psk") => "WPA2",
+        Some("wpa-eap" | "wpa-eap-suite-b-192") => "Enterprise",
+        // Static or 802.1X WEP
+        Some("none" | "ieee8021x") => "WEP",
+        Some("owe") => "OWE",
+        Some(other) => other,
+    }
+}
 
-    let Ok(settings) = SettingsProxy::new(connection).await else {
-        return ssids;
+/// Devices of NM's primary connection (the one holding the default route).
+async fn primary_connection_devices(
+    connection: &zbus::Connection,
+    nm: &NetworkManagerProxy<'_>,
+) -> Vec<zbus::zvariant::OwnedObjectPath> {
+    let Ok(primary) = nm.primary_connection().await else {
+        return Vec::new();
+    };
+    if primary.as_str() == "/" {
+        return Vec::new();
+    }
+    let Ok(builder) = ActiveConnectionProxy::builder(connection).path(primary) else {
+        return Vec::new();
     };
-    let Ok(conn_paths) = settings.list_connections().await else {
-        return ssids;
+    let Ok(ac) = builder.build().await else {
+        return Vec::new();
     };
+    ac.devices().await.unwrap_or_default()
+}
 
-    for path in &conn_paths {
-        let Ok(builder) = SettingsConnectionProxy::builder(connection).path(path) else {
-            continue;
-        };
-        let Ok(conn) = builder.build().await else {
-            continue;
-        };
-        let Ok(s) = conn.get_settings().await else {
-            continue;
-        };
+/// Whether NM's primary connection is active on the given device.
+async fn is_primary_device(connection: &zbus::Connection, device_path: &str) -> bool {
+    let Ok(nm) = NetworkManagerProxy::new(connection).await else {
+        return false;
+    };
+    primary_connection_devices(connection, &nm)
+        .await
+        .iter()
+        .any(|d| d.as_str() == device_path)
+}
+
+/// NM's view of internet reachability, from its periodic connectivity check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Not checked yet, or checking is disabled.
+    Unknown,
+    None,
+    /// A captive portal intercepts requests.
+    Portal,
+    /// Connected, but the internet isn't reachable.
+    Limited,
+    Full,
+}
 
-        if let Some(ssid) = get_wifi_ssid(&s) {
-            ssids.insert(ssid);
+impl Connectivity {
+    pub fn from_state(state: u32) -> Connectivity {
+        match state {
+            1 => Connectivity::None,
+            2 => Connectivity::Portal,
+            3 => Connectivity::Limited,
+            4 => Connectivity::Full,
+            _ => Connectivity::Unknown,
         }
     }
+}
+
+pub async fn connectivity() -> Result<Connectivity, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let nm = NetworkManagerProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to create NetworkManager proxy: {e}"))?;
+    nm.connectivity()
+        .await
+        .map(Connectivity::from_state)
+        .map_err(|e| format!("Failed to get connectivity: {e}"))
+}
+
+/// Path of the WiFi device carrying the default route, if the primary
+/// connection is a WiFi connection.
+pub async fn default_route_device() -> Result<Option<String>, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let nm = NetworkManagerProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to create NetworkManager proxy: {e}"))?;
+    default_route_wifi_device(&connection, &nm).await
+}
 
-    ssids
+async fn default_route_wifi_device(
+    connection: &zbus::Connection,
+    nm: &NetworkManagerProxy<'_>,
+) -> Result<Option<String>, String> {
+    let primary_type = nm
+        .primary_connection_type()
+        .await
+        .map_err(|e| format!("Failed to get primary connection type: {e}"))?;
+    if primary_type != "802-11-wireless" {
+        return Ok(None);
+    }
+    let devices = primary_connection_devices(connection, nm).await;
+    Ok(devices.first().map(|d| d.to_string()))
 }
 
 pub async fn list_wifi_devices() -> Result<Vec<WifiDevice>, String> {
-    let connection = zbus::Connection::system()
+    let connection = bus()
         .await
         .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
 
@@ -127,6 +685,10 @@ pub async fn list_wifi_devices() -> Result<Vec<WifiDevice>, String> {
         .await
         .map_err(|e| format!("Failed to get devices: {e}"))?;
 
+    let default_route = default_route_wifi_device(&connection, &nm)
+        .await
+        .unwrap_or_default();
+
     let mut wifi_devices = Vec::new();
     for path in &devices {
         let device = DeviceProxy::builder(&connection)
@@ -141,6 +703,8 @@ pub async fn list_wifi_devices() -> Result<Vec<WifiDevice>, String> {
             wifi_devices.push(WifiDevice {
                 path: path.to_string(),
                 interface,
+                is_default_route: default_route.as_deref() == Some(path.as_str()),
+                managed: device.managed().await.unwrap_or(true),
             });
         }
     }
@@ -152,117 +716,970 @@ pub async fn list_wifi_devices() -> Result<Vec<WifiDevice>, String> {
     Ok(wifi_devices)
 }
 
-pub async fn scan_networks(device_path: &str) -> Result<Vec<Network>, String> {
-    let connection = zbus::Connection::system()
+/// Total bytes received and sent by the device. Turns on NM's statistics
+/// refresh for the device if it's off, otherwise the counters never change.
+pub async fn device_traffic(device_path: &str) -> Result<(u64, u64), String> {
+    let connection = bus()
         .await
         .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
 
-    let wifi_path = zbus::zvariant::ObjectPath::try_from(device_path)
-        .map_err(|e| format!("Invalid device path: {e}"))?;
+    let stats = StatisticsProxy::builder(&connection)
+        .path(device_path)
+        .map_err(|e| format!("Invalid device path: {e}"))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to create statistics proxy: {e}"))?;
+
+    if stats.refresh_rate_ms().await.unwrap_or(0) == 0 {
+        stats
+            .set_refresh_rate_ms(1000)
+            .await
+            .map_err(|e| format!("Failed to enable device statistics: {e}"))?;
+    }
+
+    let rx = stats
+        .rx_bytes()
+        .await
+        .map_err(|e| format!("Failed to get received bytes: {e}"))?;
+    let tx = stats
+        .tx_bytes()
+        .await
+        .map_err(|e| format!("Failed to get sent bytes: {e}"))?;
+    Ok((rx, tx))
+}
+
+/// Path and signal strength of the access point the device is associated
+/// with, `None` when it isn't associated.
+pub async fn active_ap_strength(device_path: &str) -> Result<Option<(String, u8)>, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
 
     let wireless = WirelessProxy::builder(&connection)
-        .path(&wifi_path)
+        .path(device_path)
         .map_err(|e| format!("Invalid wireless path: {e}"))?
         .build()
         .await
         .map_err(|e| format!("Failed to create wireless proxy: {e}"))?;
 
-    // Trigger a scan (best-effort, may fail due to permissions or rate limiting)
-    let _ = wireless.request_scan(HashMap::new()).await;
-
-    let active_ap = wireless.active_access_point().await.ok();
-
-    let ap_paths = wireless
-        .get_all_access_points()
+    let ap_path = wireless
+        .active_access_point()
         .await
-        .map_err(|e| format!("Failed to get access points: {e}"))?;
+        .map_err(|e| format!("Failed to get active access point: {e}"))?;
+    if ap_path.as_str() == "/" {
+        return Ok(None);
+    }
 
-    // Collect saved WiFi SSIDs
-    let saved_ssids = saved_wifi_ssids(&connection).await;
+    let ap = AccessPointProxy::builder(&connection)
+        .path(&ap_path)
+        .map_err(|e| format!("Invalid AP path: {e}"))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to create AP proxy: {e}"))?;
+    let strength = ap
+        .strength()
+        .await
+        .map_err(|e| format!("Failed to get signal strength: {e}"))?;
 
-    let mut networks: Vec<Network> = Vec::new();
+    Ok(Some((ap_path.to_string(), strength)))
+}
 
-    for ap_path in &ap_paths {
-        let ap = AccessPointProxy::builder(&connection)
-            .path(ap_path)
-            .map_err(|e| format!("Invalid AP path: {e}"))?
-            .build()
-            .await
-            .map_err(|e| format!("Failed to create AP proxy: {e}"))?;
+/// `NM_WIFI_DEVICE_CAP_*` flags and their names. NM has no flag for SAE
+/// (WPA3), it depends on wpa_supplicant rather than the driver.
+const WIFI_DEVICE_CAPS: [(u32, &str); 10] = [
+    (0x1, "WEP40"),
+    (0x2, "WEP104"),
+    (0x4, "TKIP"),
+    (0x8, "CCMP"),
+    (0x10, "WPA"),
+    (0x20, "RSN"),
+    (0x40, "AP"),
+    (0x80, "ADHOC"),
+    (0x1000, "MESH"),
+    (0x2000, "IBSS_RSN"),
+];
+
+/// Set when the `FREQ_*` flags are valid.
+const NM_WIFI_DEVICE_CAP_FREQ_VALID: u32 = 0x100;
+
+/// `NM_WIFI_DEVICE_CAP_FREQ_*` flags and their bands.
+const WIFI_DEVICE_BANDS: [(u32, &str); 3] =
+    [(0x200, "2.4 GHz"), (0x400, "5 GHz"), (0x800, "6 GHz")];
+
+/// What a WiFi adapter supports, as reported by NM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WifiCapabilities {
+    /// `NM_WIFI_DEVICE_CAP_*` flags.
+    pub flags: u32,
+    /// Current bit rate in kb/s, 0 when not associated.
+    pub bitrate: u32,
+}
 
-        let ssid_bytes = ap.ssid().await.unwrap_or_default();
-        let ssid = String::from_utf8_lossy(&ssid_bytes).to_string();
+impl WifiCapabilities {
+    /// Names of the capabilities the adapter has, e.g. "RSN", "AP".
+    pub fn names(&self) -> Vec<&'static str> {
+        WIFI_DEVICE_CAPS
+            .iter()
+            .filter(|(flag, _)| self.flags & flag != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
 
-        // Skip hidden networks (empty SSID)
-        if ssid.is_empty() {
-            continue;
+    /// Supported bands, `None` when the driver doesn't report them.
+    pub fn bands(&self) -> Option<Vec<&'static str>> {
+        if self.flags & NM_WIFI_DEVICE_CAP_FREQ_VALID == 0 {
+            return None;
         }
+        Some(
+            WIFI_DEVICE_BANDS
+                .iter()
+                .filter(|(flag, _)| self.flags & flag != 0)
+                .map(|(_, band)| *band)
+                .collect(),
+        )
+    }
 
-        let strength = ap.strength().await.unwrap_or(0);
-        let flags = ap.flags().await.unwrap_or(0);
-        let wpa_flags = ap.wpa_flags().await.unwrap_or(0);
-        let rsn_flags = ap.rsn_flags().await.unwrap_or(0);
-
-        let is_connected = active_ap.as_ref().is_some_and(|active| active == ap_path);
-
-        let is_saved = saved_ssids.contains(&ssid);
-
-        networks.push(Network {
-            ssid,
-            strength,
-            security: security_from_flags(flags, wpa_flags, rsn_flags),
-            is_connected,
-            is_saved,
-            ap_path: ap_path.to_string(),
-            device_path: wifi_path.to_string(),
-        });
+    /// The highest band the adapter can use, `None` when unknown.
+    pub fn max_band(&self) -> Option<&'static str> {
+        self.bands()?.last().copied()
     }
+}
 
-    // Deduplicate by SSID: prefer connected, then strongest signal
-    networks.sort_by(|a, b| {
-        b.is_connected
-            .cmp(&a.is_connected)
-            .then(b.strength.cmp(&a.strength))
-    });
-    let mut seen = std::collections::HashSet::new();
-    networks.retain(|n| seen.insert(n.ssid.clone()));
+/// Capabilities of the WiFi adapter at `device_path`.
+pub async fn wifi_capabilities(device_path: String) -> Result<WifiCapabilities, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
 
-    Ok(networks)
-}
+    let wireless = WirelessProxy::builder(&connection)
+        .path(device_path)
+        .map_err(|e| format!("Invalid wireless path: {e}"))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to create wireless proxy: {e}"))?;
 
-/// Find a saved connection profile matching the given SSID.
-/// Returns the connection object path if found.
-async fn find_saved_connection(
-    connection: &zbus::Connection,
-    ssid: &str,
-) -> Result<Option<zbus::zvariant::OwnedObjectPath>, String> {
-    let settings = SettingsProxy::new(connection)
+    let flags = wireless
+        .wireless_capabilities()
         .await
-        .map_err(|e| format!("Failed to create Settings proxy: {e}"))?;
+        .map_err(|e| format!("Failed to get device capabilities: {e}"))?;
+    let bitrate = wireless
+        .bitrate()
+        .await
+        .map_err(|e| format!("Failed to get bit rate: {e}"))?;
 
-    let connections = settings
-        .list_connections()
+    Ok(WifiCapabilities { flags, bitrate })
+}
+
+/// Signal strength of the strongest access point advertising `ssid` on the
+/// device, without a scan. `None` when no access point has the SSID or NM
+/// can't be reached.
+pub async fn strength_of(device_path: &str, ssid: &[u8]) -> Option<u8> {
+    let connection = bus().await.ok()?;
+    let wireless = WirelessProxy::builder(&connection)
+        .path(device_path)
+        .ok()?
+        .build()
         .await
-        .map_err(|e| format!("Failed to list connections: {e}"))?;
+        .ok()?;
 
-    for path in connections {
-        let conn = SettingsConnectionProxy::builder(connection)
-            .path(&path)
-            .map_err(|e| format!("Invalid connection path: {e}"))?
+    let ap_strength = async |ap_path: &zbus::zvariant::OwnedObjectPath| -> Option<(Vec<u8>, u8)> {
+        let ap = AccessPointProxy::builder(&connection)
+            .path(ap_path)
+            .ok()?
             .build()
             .await
-            .map_err(|e| format!("Failed to create connection proxy: {e}"))?;
+            .ok()?;
+        Some((ap.ssid().await.ok()?, ap.strength().await.ok()?))
+    };
 
-        let Ok(s) = conn.get_settings().await else {
-            continue;
-        };
+    // The connected network is the one polled most, its AP is a single lookup
+    let active_ap = wireless.active_access_point().await.ok();
+    if let Some(active_ap) = active_ap.as_ref().filter(|ap| ap.as_str() != "/")
+        && let Some((ap_ssid, strength)) = ap_strength(active_ap).await
+        && ap_ssid == ssid
+    {
+        return Some(strength);
+    }
 
-        if get_wifi_ssid(&s).as_deref() == Some(ssid) {
-            return Ok(Some(path));
+    let mut best = None;
+    for ap_path in wireless.get_all_access_points().await.ok()? {
+        if let Some((ap_ssid, strength)) = ap_strength(&ap_path).await
+            && ap_ssid == ssid
+        {
+            best = best.max(Some(strength));
         }
     }
+    best
+}
 
-    Ok(None)
+/// Run `fetch` on every item, `batch` items at a time with the items of a
+/// batch fetched concurrently. The results are in the order of `items`.
+/// `on_batch` is called with the results so far after every batch but the
+/// last.
+async fn fetch_in_batches<T, R, F>(
+    items: Vec<T>,
+    batch: usize,
+    fetch: impl Fn(T) -> F,
+    mut on_batch: impl FnMut(&[R]),
+) -> Vec<R>
+where
+    F: std::future::Future<Output = R>,
+{
+    let mut results = Vec::with_capacity(items.len());
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        if !results.is_empty() {
+            on_batch(&results);
+        }
+        let fetches = items.by_ref().take(batch).map(&fetch);
+        results.extend(iced::futures::future::join_all(fetches).await);
+    }
+    results
+}
+
+/// Scan a device for networks. With many access points around this takes a
+/// while, so `on_partial` is called with the networks found so far every
+/// `SCAN_BATCH` access points.
+pub async fn scan_networks(
+    device_path: &str,
+    mut on_partial: impl FnMut(Vec<Network>),
+) -> Result<Scan, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+
+    let wifi_path = zbus::zvariant::ObjectPath::try_from(device_path)
+        .map_err(|e| format!("Invalid device path: {e}"))?;
+
+    let wireless = WirelessProxy::builder(&connection)
+        .path(&wifi_path)
+        .map_err(|e| format!("Invalid wireless path: {e}"))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to create wireless proxy: {e}"))?;
+
+    // Trigger a scan (best-effort, may fail due to permissions or rate limiting).
+    // Permission failures are reported so the UI can explain why the list
+    // doesn't refresh.
+    let scan_denied = match wireless.request_scan(HashMap::new()).await {
+        Err(e) => is_permission_error(&e),
+        Ok(()) => false,
+    };
+
+    let active_ap = wireless.active_access_point().await.ok();
+
+    let ap_paths = wireless
+        .get_all_access_points()
+        .await
+        .map_err(|e| format!("Failed to get access points: {e}"))?;
+
+    // Saved WiFi profiles
+    let profiles = saved_profiles(&connection).await.unwrap_or_default();
+
+    let ip4 = device_ip4(&connection, device_path).await;
+    let active_settings = active_profile_settings(&connection, device_path).await;
+    let ip4_method = active_settings.as_ref().and_then(ip4_method);
+    let connected_security = active_settings.as_ref().map(profile_security);
+    let is_primary_device = is_primary_device(&connection, device_path).await;
+
+    let read_ap = |ap_path: zbus::zvariant::OwnedObjectPath| {
+        let (connection, profiles) = (&connection, &profiles);
+        let (active_ap, wifi_path) = (&active_ap, &wifi_path);
+        async move {
+            let ap = AccessPointProxy::builder(connection)
+                .path(&ap_path)
+                .map_err(|e| format!("Invalid AP path: {e}"))?
+                .build()
+                .await
+                .map_err(|e| format!("Failed to create AP proxy: {e}"))?;
+
+            let (ssid_bytes, strength, frequency, bssid, flags, wpa_flags, rsn_flags) = iced::futures::join!(
+                ap.ssid(),
+                ap.strength(),
+                ap.frequency(),
+                ap.hw_address(),
+                ap.flags(),
+                ap.wpa_flags(),
+                ap.rsn_flags(),
+            );
+            let ssid_bytes = ssid_bytes.unwrap_or_default();
+            let ssid = String::from_utf8_lossy(&ssid_bytes).to_string();
+
+            // Skip hidden networks (empty SSID)
+            if ssid.is_empty() {
+                return Ok(None);
+            }
+
+            let is_connected = active_ap.as_ref().is_some_and(|active| *active == ap_path);
+
+            let profile = find_profile(profiles, &ssid_bytes);
+            let autoconnect = profile.map(|p| AutoConnect {
+                enabled: p.autoconnect,
+                priority: p.autoconnect_priority,
+            });
+
+            Ok(Some(Network {
+                ssid,
+                ssid_bytes,
+                strength: strength.unwrap_or(0),
+                security: security_from_flags(
+                    flags.unwrap_or(0),
+                    wpa_flags.unwrap_or(0),
+                    rsn_flags.unwrap_or(0),
+                ),
+                is_connected,
+                is_primary: is_connected && is_primary_device,
+                is_saved: autoconnect.is_some(),
+                autoconnect,
+                metered: profile.is_some_and(|p| p.metered),
+                pinned_bssid: profile.and_then(|p| p.bssid.clone()),
+                ap_count: 1,
+                frequency: frequency.unwrap_or(0),
+                bssid: bssid.unwrap_or_default(),
+                access_points: Vec::new(),
+                ap_path: ap_path.to_string(),
+                device_path: wifi_path.to_string(),
+            }))
+        }
+    };
+
+    let found_networks = |results: &[Result<Option<Network>, String>]| -> Vec<Network> {
+        results
+            .iter()
+            .filter_map(|result| result.as_ref().ok()?.clone())
+            .collect()
+    };
+
+    let results = fetch_in_batches(ap_paths, SCAN_BATCH, read_ap, |results| {
+        let mut partial = found_networks(results);
+        dedup_networks(&mut partial);
+        on_partial(partial);
+    })
+    .await;
+    if let Some(Err(e)) = results.iter().find(|result| result.is_err()) {
+        return Err(e.clone());
+    }
+    let mut networks = found_networks(&results);
+    dedup_networks(&mut networks);
+
+    let last_scan_age = match wireless.last_scan().await {
+        Ok(ms) if ms >= 0 => {
+            boot_time().and_then(|now| now.checked_sub(std::time::Duration::from_millis(ms as u64)))
+        }
+        _ => None,
+    };
+
+    let ip6 = device_ip6(&connection, device_path).await;
+    let bitrate = wireless.bitrate().await.unwrap_or(0);
+
+    Ok(Scan {
+        networks,
+        scan_denied,
+        ip4,
+        ip6,
+        bitrate,
+        ip4_method,
+        connected_security,
+        last_scan_age,
+    })
+}
+
+/// A saved WiFi connection profile, with the settings used to rank profiles
+/// that share an SSID.
+#[derive(Debug, Clone)]
+struct SavedProfile {
+    path: zbus::zvariant::OwnedObjectPath,
+    /// For display, see `Network::ssid`.
+    ssid: String,
+    ssid_bytes: Vec<u8>,
+    /// `connection.autoconnect`, defaults to true.
+    autoconnect: bool,
+    /// `connection.autoconnect-priority`, defaults to 0.
+    autoconnect_priority: i32,
+    /// `connection.metered` is "yes".
+    metered: bool,
+    /// `802-11-wireless.bssid`, formatted like `AccessPoint::bssid`.
+    bssid: Option<String>,
+    /// Security type, as named in scans.
+    security: String,
+    /// `connection.timestamp`: when the profile was last activated
+    /// successfully, in seconds since the epoch. 0 if never used.
+    timestamp: u64,
+}
+
+impl SavedProfile {
+    /// Returns `None` for profiles that aren't WiFi profiles.
+    fn from_settings(
+        path: zbus::zvariant::OwnedObjectPath,
+        s: &HashMap<String, HashMap<String, zbus::zvariant::OwnedValue>>,
+    ) -> Option<SavedProfile> {
+        let ssid_bytes = get_wifi_ssid_bytes(s)?;
+        let ssid = String::from_utf8_lossy(&ssid_bytes).to_string();
+        let conn = s.get("connection");
+        let autoconnect = conn
+            .and_then(|c| c.get("autoconnect"))
+            .and_then(|v| bool::try_from(v).ok())
+            .unwrap_or(true);
+        let autoconnect_priority = conn
+            .and_then(|c| c.get("autoconnect-priority"))
+            .and_then(|v| i32::try_from(v).ok())
+            .unwrap_or(0);
+        let metered = conn
+            .and_then(|c| c.get("metered"))
+            .and_then(|v| i32::try_from(v).ok())
+            == Some(NM_METERED_YES);
+        let timestamp = conn
+            .and_then(|c| c.get("timestamp"))
+            .and_then(|v| u64::try_from(v).ok())
+            .unwrap_or(0);
+        let bssid = s
+            .get("802-11-wireless")
+            .and_then(|w| w.get("bssid"))
+            .and_then(|v| <&zbus::zvariant::Array>::try_from(v).ok())
+            .map(|bytes| {
+                bytes
+                    .iter()
+                    .filter_map(|b| u8::try_from(b).ok())
+                    .map(|b| format!("{b:02X}"))
+                    .collect::<Vec<_>>()
+                    .join(":")
+            })
+            .filter(|bssid| !bssid.is_empty());
+        let key_mgmt = s
+            .get("802-11-wireless-security")
+            .and_then(|s| s.get("key-mgmt"))
+            .and_then(|v| <&str>::try_from(v).ok());
+        let security = security_name(key_mgmt).to_string();
+        Some(SavedProfile {
+            path,
+            ssid,
+            ssid_bytes,
+            autoconnect,
+            autoconnect_priority,
+            metered,
+            bssid,
+            security,
+            timestamp,
+        })
+    }
+}
+
+/// Pick the profile to use for an SSID among the saved profiles. Uses the
+/// same order as NM's autoconnect: highest priority first, then most recently
+/// used. Ties go to the profile listed first.
+fn find_profile(profiles: &[SavedProfile], ssid: impl AsRef<[u8]>) -> Option<&SavedProfile> {
+    profiles
+        .iter()
+        .filter(|p| p.ssid_bytes == ssid.as_ref())
+        .rev()
+        .max_by_key(|p| (p.autoconnect_priority, p.timestamp))
+}
+
+/// A saved WiFi network, for the list of saved networks.
+#[derive(Debug, Clone)]
+pub struct SavedNetwork {
+    pub ssid: String,
+    pub ssid_bytes: Vec<u8>,
+    /// Security type of the profile, as named in scans, e.g. "WPA2".
+    pub security: String,
+    pub autoconnect: bool,
+    pub autoconnect_priority: i32,
+}
+
+/// The saved WiFi networks, in range or not, sorted by SSID. SSIDs with
+/// several profiles are listed once, with the profile NM would use.
+pub async fn list_saved_networks() -> Result<Vec<SavedNetwork>, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let profiles = saved_profiles(&connection).await?;
+
+    let mut ssids: Vec<&[u8]> = profiles.iter().map(|p| p.ssid_bytes.as_slice()).collect();
+    // Case-insensitive, ties broken by the bytes so duplicates end up next to
+    // each other
+    ssids.sort_by_key(|ssid| (String::from_utf8_lossy(ssid).to_lowercase(), *ssid));
+    ssids.dedup();
+    Ok(ssids
+        .into_iter()
+        .filter_map(|ssid| find_profile(&profiles, ssid))
+        .map(|profile| SavedNetwork {
+            ssid: profile.ssid.clone(),
+            ssid_bytes: profile.ssid_bytes.clone(),
+            security: profile.security.clone(),
+            autoconnect: profile.autoconnect,
+            autoconnect_priority: profile.autoconnect_priority,
+        })
+        .collect())
+}
+
+/// All saved WiFi connection profiles.
+async fn saved_profiles(connection: &zbus::Connection) -> Result<Vec<SavedProfile>, String> {
+    let settings = SettingsProxy::new(connection)
+        .await
+        .map_err(|e| format!("Failed to create Settings proxy: {e}"))?;
+
+    let connections = settings
+        .list_connections()
+        .await
+        .map_err(|e| format!("Failed to list connections: {e}"))?;
+
+    let mut profiles = Vec::new();
+    for path in connections {
+        let conn = SettingsConnectionProxy::builder(connection)
+            .path(&path)
+            .map_err(|e| format!("Invalid connection path: {e}"))?
+            .build()
+            .await
+            .map_err(|e| format!("Failed to create connection proxy: {e}"))?;
+
+        let Ok(s) = conn.get_settings().await else {
+            continue;
+        };
+
+        profiles.extend(SavedProfile::from_settings(path, &s));
+    }
+
+    Ok(profiles)
+}
+
+/// Delete every saved profile for `ssid`.
+pub async fn forget_network(ssid: Vec<u8>) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+
+    for profile in saved_profiles(&connection).await? {
+        if profile.ssid_bytes != ssid {
+            continue;
+        }
+        SettingsConnectionProxy::builder(&connection)
+            .path(&profile.path)
+            .map_err(|e| format!("Invalid connection path: {e}"))?
+            .build()
+            .await
+            .map_err(|e| format!("Failed to create connection proxy: {e}"))?
+            .delete()
+            .await
+            .map_err(|e| format!("Failed to forget {}: {e}", profile.ssid))?;
+    }
+
+    Ok(())
+}
+
+/// Find a saved connection profile matching the given SSID.
+/// Returns the connection object path if found. When several profiles match,
+/// the one picked by `find_profile` is returned.
+async fn find_saved_connection(
+    connection: &zbus::Connection,
+    ssid: &[u8],
+) -> Result<Option<zbus::zvariant::OwnedObjectPath>, String> {
+    let profiles = saved_profiles(connection).await?;
+    Ok(find_profile(&profiles, ssid).map(|p| p.path.clone()))
+}
+
+/// IPv4 settings of a profile, as edited in the UI.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Ip4Settings {
+    /// `ipv4.method` "manual" rather than "auto" (DHCP).
+    pub manual: bool,
+    /// Address with prefix length, e.g. "192.168.1.20/24". Only used with
+    /// `manual`.
+    pub address: String,
+    /// Only used with `manual`.
+    pub gateway: String,
+    /// DNS servers, separated by commas or spaces. With DHCP these replace the
+    /// servers the DHCP server hands out.
+    pub dns: String,
+}
+
+fn parse_ip4(s: &str) -> Result<std::net::Ipv4Addr, String> {
+    s.parse().map_err(|_| format!("Invalid IPv4 address: {s}"))
+}
+
+impl Ip4Settings {
+    fn from_settings(settings: &proxy::ConnectionSettings) -> Ip4Settings {
+        let Some(ipv4) = settings.get("ipv4") else {
+            return Ip4Settings::default();
+        };
+        let get_str = |key: &str| -> Option<String> {
+            let value: &str = ipv4.get(key)?.try_into().ok()?;
+            Some(value.to_string())
+        };
+
+        let address = ipv4
+            .get("address-data")
+            .and_then(|v| v.try_clone().ok())
+            .and_then(|v| Vec::<HashMap<String, zbus::zvariant::OwnedValue>>::try_from(v).ok())
+            .and_then(|addresses| {
+                let first = addresses.first()?;
+                let address: &str = first.get("address")?.try_into().ok()?;
+                let prefix = u32::try_from(first.get("prefix")?).ok()?;
+                Some(format!("{address}/{prefix}"))
+            });
+
+        let dns = ipv4
+            .get("dns")
+            .and_then(|v| v.try_clone().ok())
+            .and_then(|v| Vec::<u32>::try_from(v).ok())
+            .unwrap_or_default()
+            .into_iter()
+            // Stored in network byte order
+            .map(|ip| std::net::Ipv4Addr::from(ip.to_ne_bytes()).to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Ip4Settings {
+            manual: get_str("method").as_deref() == Some("manual"),
+            address: address.unwrap_or_default(),
+            gateway: get_str("gateway").unwrap_or_default(),
+            dns,
+        }
+    }
+
+    /// Check the settings can be saved, e.g. before closing an editor.
+    pub fn check(&self) -> Result<(), String> {
+        self.section().map(|_| ())
+    }
+
+    /// The properties of the `ipv4` section these settings set.
+    fn section(&self) -> Result<HashMap<&'static str, zbus::zvariant::Value<'static>>, String> {
+        use zbus::zvariant::Value;
+
+        let dns = self
+            .dns
+            .split([',', ' '])
+            .filter(|s| !s.is_empty())
+            .map(|s| parse_ip4(s).map(|ip| u32::from_ne_bytes(ip.octets())))
+            .collect::<Result<Vec<u32>, String>>()?;
+
+        let mut ipv4: HashMap<&str, Value<'_>> = HashMap::new();
+        if self.manual {
+            if self.address.is_empty() {
+                return Err("A static address is needed".to_string());
+            }
+            let (address, prefix) = match self.address.split_once('/') {
+                Some((address, prefix)) => (
+                    address,
+                    prefix
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|p| (1..=32).contains(p))
+                        .ok_or_else(|| format!("Invalid prefix length: {prefix}"))?,
+                ),
+                None => (self.address.as_str(), 24),
+            };
+            let address = parse_ip4(address)?;
+            let mut address_data: HashMap<&str, Value<'_>> = HashMap::new();
+            address_data.insert("address", address.to_string().into());
+            address_data.insert("prefix", prefix.into());
+            ipv4.insert("method", "manual".into());
+            ipv4.insert("address-data", vec![address_data].into());
+            if !self.gateway.is_empty() {
+                let gateway = parse_ip4(&self.gateway)?;
+                ipv4.insert("gateway", gateway.to_string().into());
+            }
+        } else {
+            ipv4.insert("method", "auto".into());
+        }
+
+        ipv4.insert("ignore-auto-dns", (!self.manual && !dns.is_empty()).into());
+        if !dns.is_empty() {
+            ipv4.insert("dns", dns.into());
+        }
+        Ok(ipv4)
+    }
+
+    /// Write these settings into the `ipv4` section of `settings`, leaving
+    /// unrelated properties alone.
+    fn apply(&self, settings: &mut proxy::ConnectionSettings) -> Result<(), String> {
+        let section = self.section()?;
+
+        let ipv4 = settings.entry("ipv4".to_string()).or_default();
+        // Deprecated forms of the properties set below, NM rejects updates
+        // that disagree with them
+        for key in ["addresses", "address-data", "gateway", "dns", "dns-data"] {
+            ipv4.remove(key);
+        }
+        for (key, value) in section {
+            let value = zbus::zvariant::OwnedValue::try_from(value)
+                .map_err(|e| format!("Invalid setting value: {e}"))?;
+            ipv4.insert(key.to_string(), value);
+        }
+        Ok(())
+    }
+}
+
+async fn saved_profile_proxy<'a>(
+    connection: &'a zbus::Connection,
+    ssid: &[u8],
+) -> Result<SettingsConnectionProxy<'a>, String> {
+    let profiles = saved_profiles(connection).await?;
+    let path = find_profile(&profiles, ssid)
+        .map(|p| p.path.clone())
+        .ok_or_else(|| format!("No saved profile for {}", String::from_utf8_lossy(ssid)))?;
+    SettingsConnectionProxy::builder(connection)
+        .path(path)
+        .map_err(|e| format!("Invalid connection path: {e}"))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to create connection proxy: {e}"))
+}
+
+/// All settings of a profile, to be changed and passed to `update`.
+async fn settings_for_update(
+    profile: &SettingsConnectionProxy<'_>,
+    ssid: &str,
+) -> Result<proxy::ConnectionSettings, String> {
+    let mut settings = profile
+        .get_settings()
+        .await
+        .map_err(|e| format!("Failed to read settings of {ssid}: {e}"))?;
+
+    // Update replaces the whole profile, secrets included. They aren't part
+    // of `get_settings`, so add them back: the PSK or WEP keys, and the
+    // Enterprise password.
+    for section in ["802-11-wireless-security", "802-1x"] {
+        if settings.contains_key(section)
+            && let Ok(secrets) = profile.get_secrets(section).await
+        {
+            for (setting, values) in secrets {
+                settings.entry(setting).or_default().extend(values);
+            }
+        }
+    }
+    Ok(settings)
+}
+
+// NMMetered values a profile can have
+// https://networkmanager.dev/docs/api/latest/nm-dbus-types.html#NMMetered
+const NM_METERED_YES: i32 = 1;
+const NM_METERED_NO: i32 = 2;
+
+/// Set `connection.metered` of the saved profile for `ssid`.
+pub async fn set_metered(ssid_bytes: Vec<u8>, metered: bool) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let ssid = String::from_utf8_lossy(&ssid_bytes).to_string();
+    let profile = saved_profile_proxy(&connection, &ssid_bytes).await?;
+    let mut settings = settings_for_update(&profile, &ssid).await?;
+    let value = if metered {
+        NM_METERED_YES
+    } else {
+        NM_METERED_NO
+    };
+    settings
+        .entry("connection".to_string())
+        .or_default()
+        .insert(
+            "metered".to_string(),
+            zbus::zvariant::OwnedValue::from(value),
+        );
+    profile
+        .update(settings)
+        .await
+        .map_err(|e| format!("Failed to save settings of {ssid}: {e}"))
+}
+
+/// Parse a BSSID like "AA:BB:CC:DD:EE:FF" into its 6 bytes.
+fn parse_bssid(bssid: &str) -> Result<Vec<u8>, String> {
+    let bytes = bssid
+        .split(':')
+        .map(|b| {
+            let hex = b.len() == 2 && b.bytes().all(|c| c.is_ascii_hexdigit());
+            hex.then(|| u8::from_str_radix(b, 16).ok()).flatten()
+        })
+        .collect::<Option<Vec<u8>>>()
+        .filter(|bytes| bytes.len() == 6);
+    bytes.ok_or_else(|| format!("Invalid BSSID: {bssid}"))
+}
+
+/// Lock a saved profile to the access point with `bssid`, or unlock it with
+/// `None`.
+async fn pin_bssid(
+    profile: &SettingsConnectionProxy<'_>,
+    ssid: &str,
+    bssid: Option<&str>,
+) -> Result<(), String> {
+    let mut settings = settings_for_update(profile, ssid).await?;
+    let wireless = settings.entry("802-11-wireless".to_string()).or_default();
+    match bssid {
+        Some(bssid) => {
+            let bytes = zbus::zvariant::Value::from(parse_bssid(bssid)?);
+            let bytes = zbus::zvariant::OwnedValue::try_from(bytes)
+                .map_err(|e| format!("Invalid setting value: {e}"))?;
+            wireless.insert("bssid".to_string(), bytes);
+        }
+        None => {
+            wireless.remove("bssid");
+        }
+    }
+    profile
+        .update(settings)
+        .await
+        .map_err(|e| format!("Failed to save settings of {ssid}: {e}"))
+}
+
+/// Stop locking the saved profile for `ssid` to one access point.
+pub async fn unpin_bssid(ssid_bytes: Vec<u8>) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let ssid = String::from_utf8_lossy(&ssid_bytes).to_string();
+    let profile = saved_profile_proxy(&connection, &ssid_bytes).await?;
+    pin_bssid(&profile, &ssid, None).await
+}
+
+/// Set `connection.autoconnect` of the saved profile for `ssid`.
+pub async fn set_autoconnect(ssid_bytes: Vec<u8>, enabled: bool) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let ssid = String::from_utf8_lossy(&ssid_bytes).to_string();
+    let profile = saved_profile_proxy(&connection, &ssid_bytes).await?;
+    let mut settings = settings_for_update(&profile, &ssid).await?;
+    settings
+        .entry("connection".to_string())
+        .or_default()
+        .insert(
+            "autoconnect".to_string(),
+            zbus::zvariant::OwnedValue::from(enabled),
+        );
+    profile
+        .update(settings)
+        .await
+        .map_err(|e| format!("Failed to save settings of {ssid}: {e}"))
+}
+
+/// What a guest needs to join a saved network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedNetwork {
+    pub ssid: String,
+    /// As `profile_security` names it, e.g. "WPA2".
+    pub security: String,
+    /// `None` for open networks.
+    pub password: Option<String>,
+    pub hidden: bool,
+}
+
+/// The saved profile for `ssid` with its password, to share it. NM only
+/// hands out passwords to administrators and the profile's owner.
+pub async fn shared_network(ssid_bytes: Vec<u8>) -> Result<SharedNetwork, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let ssid = String::from_utf8_lossy(&ssid_bytes).to_string();
+    let profile = saved_profile_proxy(&connection, &ssid_bytes).await?;
+    let settings = profile
+        .get_settings()
+        .await
+        .map_err(|e| format!("Failed to read settings of {ssid}: {e}"))?;
+
+    let security = profile_security(&settings);
+    let hidden = settings
+        .get("802-11-wireless")
+        .and_then(|wireless| wireless.get("hidden"))
+        .and_then(|v| bool::try_from(v).ok())
+        .unwrap_or(false);
+    let key = match security.as_str() {
+        "Open" | "OWE" => {
+            return Ok(SharedNetwork {
+                ssid,
+                security,
+                password: None,
+                hidden,
+            });
+        }
+        "Enterprise" => return Err(format!("{ssid} uses per-user logins, it can't be shared")),
+        "WEP" => {
+            let index: u32 = settings
+                .get("802-11-wireless-security")
+                .and_then(|security| security.get("wep-tx-keyidx"))
+                .and_then(|v| v.try_into().ok())
+                .unwrap_or(0);
+            format!("wep-key{index}")
+        }
+        _ => "psk".to_string(),
+    };
+
+    let secrets = profile
+        .get_secrets("802-11-wireless-security")
+        .await
+        .map_err(|e| {
+            if is_permission_error(&e) {
+                format!(
+                    "Not allowed to read the password of {ssid}, this needs administrator rights"
+                )
+            } else {
+                format!("Failed to read the password of {ssid}: {e}")
+            }
+        })?;
+    let password = secrets
+        .get("802-11-wireless-security")
+        .and_then(|secrets| secrets.get(&key))
+        .and_then(|v| String::try_from(v.clone()).ok())
+        .filter(|password| !password.is_empty())
+        .ok_or_else(|| {
+            format!(
+                "NetworkManager doesn't store the password of {ssid}, it may be in your keyring"
+            )
+        })?;
+
+    Ok(SharedNetwork {
+        ssid,
+        security,
+        password: Some(password),
+        hidden,
+    })
+}
+
+/// IPv4 settings of the saved profile for `ssid`.
+pub async fn load_ip4_settings(ssid_bytes: Vec<u8>) -> Result<Ip4Settings, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let ssid = String::from_utf8_lossy(&ssid_bytes).to_string();
+    let profile = saved_profile_proxy(&connection, &ssid_bytes).await?;
+    let settings = profile
+        .get_settings()
+        .await
+        .map_err(|e| format!("Failed to read settings of {ssid}: {e}"))?;
+    Ok(Ip4Settings::from_settings(&settings))
+}
+
+/// Update the IPv4 settings of the saved profile for `ssid`. With
+/// `reactivate_on`, the profile is then activated on that device so the
+/// changes take effect.
+pub async fn save_ip4_settings(
+    ssid_bytes: Vec<u8>,
+    ip4: Ip4Settings,
+    reactivate_on: Option<String>,
+) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let ssid = String::from_utf8_lossy(&ssid_bytes).to_string();
+    let profile = saved_profile_proxy(&connection, &ssid_bytes).await?;
+    let mut settings = settings_for_update(&profile, &ssid).await?;
+    ip4.apply(&mut settings)?;
+    profile
+        .update(settings)
+        .await
+        .map_err(|e| format!("Failed to save settings of {ssid}: {e}"))?;
+
+    if let Some(device_path) = reactivate_on {
+        let nm = NetworkManagerProxy::new(&connection)
+            .await
+            .map_err(|e| format!("Failed to create NetworkManager proxy: {e}"))?;
+        let device_path = zbus::zvariant::ObjectPath::try_from(device_path.as_str())
+            .map_err(|e| format!("Invalid device path: {e}"))?;
+        let root = zbus::zvariant::ObjectPath::from_static_str_unchecked("/");
+        nm.activate_connection(profile.inner().path(), &device_path, &root)
+            .await
+            .map_err(|e| format!("Failed to reactivate {ssid}: {e}"))?;
+    }
+
+    Ok(())
 }
 
 // NMActiveConnectionState
@@ -273,39 +1690,533 @@ const NM_ACTIVE_CONNECTION_STATE_ACTIVATED: u32 = 2;
 const NM_ACTIVE_CONNECTION_STATE_DEACTIVATING: u32 = 3;
 const NM_ACTIVE_CONNECTION_STATE_DEACTIVATED: u32 = 4;
 
-/// Poll an active connection until it reaches Activated or fails.
+// NMDeviceState values seen while a device activates
+// https://networkmanager.dev/docs/api/latest/nm-dbus-types.html#NMDeviceState
+const NM_DEVICE_STATE_PREPARE: u32 = 40;
+const NM_DEVICE_STATE_CONFIG: u32 = 50;
+const NM_DEVICE_STATE_NEED_AUTH: u32 = 60;
+const NM_DEVICE_STATE_IP_CONFIG: u32 = 70;
+const NM_DEVICE_STATE_IP_CHECK: u32 = 80;
+const NM_DEVICE_STATE_SECONDARIES: u32 = 90;
+const NM_DEVICE_STATE_ACTIVATED: u32 = 100;
+const NM_DEVICE_STATE_FAILED: u32 = 120;
+
+/// User-facing phase of an ongoing connection attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectPhase {
+    Starting,
+    Preparing,
+    Associating,
+    Authenticating,
+    ObtainingIp,
+    Connected,
+}
+
+impl ConnectPhase {
+    fn from_device_state(state: u32) -> Option<ConnectPhase> {
+        match state {
+            NM_DEVICE_STATE_PREPARE => Some(ConnectPhase::Preparing),
+            NM_DEVICE_STATE_CONFIG => Some(ConnectPhase::Associating),
+            NM_DEVICE_STATE_NEED_AUTH => Some(ConnectPhase::Authenticating),
+            NM_DEVICE_STATE_IP_CONFIG | NM_DEVICE_STATE_IP_CHECK | NM_DEVICE_STATE_SECONDARIES => {
+                Some(ConnectPhase::ObtainingIp)
+            }
+            NM_DEVICE_STATE_ACTIVATED => Some(ConnectPhase::Connected),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ConnectPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ConnectPhase::Starting => "Connecting",
+            ConnectPhase::Preparing => "Preparing",
+            ConnectPhase::Associating => "Associating",
+            ConnectPhase::Authenticating => "Authenticating",
+            ConnectPhase::ObtainingIp => "Obtaining IP",
+            ConnectPhase::Connected => "Connected",
+        })
+    }
+}
+
+// NMActiveConnectionStateReason values that mean the credentials were rejected
+// https://networkmanager.dev/docs/api/latest/nm-dbus-types.html#NMActiveConnectionStateReason
+const NM_ACTIVE_CONNECTION_STATE_REASON_NO_SECRETS: u32 = 9;
+const NM_ACTIVE_CONNECTION_STATE_REASON_LOGIN_FAILED: u32 = 10;
+
+// NMDeviceStateReason values that mean the credentials were rejected. A wrong
+// PSK deactivates the active connection with DEVICE_DISCONNECTED, only the
+// device says why.
+// https://networkmanager.dev/docs/api/latest/nm-dbus-types.html#NMDeviceStateReason
+const NM_DEVICE_STATE_REASON_NO_SECRETS: u32 = 7;
+const NM_DEVICE_STATE_REASON_SUPPLICANT_DISCONNECT: u32 = 8;
+const NM_DEVICE_STATE_REASON_SUPPLICANT_CONFIG_FAILED: u32 = 9;
+const NM_DEVICE_STATE_REASON_SUPPLICANT_FAILED: u32 = 10;
+const NM_DEVICE_STATE_REASON_SUPPLICANT_TIMEOUT: u32 = 11;
+
+fn is_device_auth_failure(reason: u32) -> bool {
+    matches!(
+        reason,
+        NM_DEVICE_STATE_REASON_NO_SECRETS
+            | NM_DEVICE_STATE_REASON_SUPPLICANT_DISCONNECT
+            | NM_DEVICE_STATE_REASON_SUPPLICANT_CONFIG_FAILED
+            | NM_DEVICE_STATE_REASON_SUPPLICANT_FAILED
+            | NM_DEVICE_STATE_REASON_SUPPLICANT_TIMEOUT
+    )
+}
+
+/// The error for an active connection deactivated with `reason`, given the
+/// reason of the device's last state change, if any.
+fn deactivation_error(reason: u32, device_reason: Option<u32>) -> ActivationError {
+    match reason {
+        NM_ACTIVE_CONNECTION_STATE_REASON_NO_SECRETS
+        | NM_ACTIVE_CONNECTION_STATE_REASON_LOGIN_FAILED => ActivationError::AuthFailed,
+        _ if device_reason.is_some_and(is_device_auth_failure) => ActivationError::AuthFailed,
+        _ => deactivation_reason(reason)
+            .map_or(ActivationError::Failed, ActivationError::FailedBecause),
+    }
+}
+
+/// What went wrong for the other NMActiveConnectionStateReason values worth
+/// telling the user about.
+fn deactivation_reason(reason: u32) -> Option<&'static str> {
+    match reason {
+        3 => Some("the device disconnected"),
+        5 => Some("the IP configuration was invalid"),
+        6 => Some("the connection attempt timed out"),
+        11 => Some("the profile was removed"),
+        12 => Some("a connection it depends on failed"),
+        14 => Some("the device was removed"),
+        _ => None,
+    }
+}
+
+/// Why an activation didn't succeed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ActivationError {
+    /// NM rejected the credentials (no or wrong secrets).
+    AuthFailed,
+    Failed,
+    /// NM gave a reason for the failure, see `deactivation_reason`.
+    FailedBecause(&'static str),
+    TimedOut,
+    /// Couldn't even watch the activation.
+    DBus(String),
+}
+
+impl std::fmt::Display for ActivationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActivationError::AuthFailed => write!(f, "Incorrect password"),
+            ActivationError::Failed => write!(f, "Connection failed"),
+            ActivationError::FailedBecause(reason) => write!(f, "Connection failed: {reason}"),
+            ActivationError::TimedOut => write!(f, "Connection timed out"),
+            ActivationError::DBus(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Why `connect` or `reconnect` failed.
+#[derive(Debug, Clone)]
+pub enum ConnectError {
+    /// NM rejected the secrets stored in the saved profile for the SSID, or
+    /// the password of a new profile that was kept. The profile is kept,
+    /// connecting with `ConnectOptions::replace_saved` recreates it with a new
+    /// password.
+    SavedSecretsRejected,
+    /// NM rejected the password of a new profile, which was deleted, see
+    /// `ConnectOptions::forget_on_auth_failure`.
+    PasswordRejected,
+    Other(String),
+}
+
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectError::SavedSecretsRejected => write!(f, "Saved password was rejected"),
+            ConnectError::PasswordRejected => write!(f, "{}", ActivationError::AuthFailed),
+            ConnectError::Other(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<String> for ConnectError {
+    fn from(e: String) -> Self {
+        ConnectError::Other(e)
+    }
+}
+
+/// How long an activation may take before we give up on it.
+const ACTIVATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Changes watched while waiting for an activation.
+enum ActivationEvent {
+    /// StateChanged signal of the active connection.
+    Connection { state: u32, reason: u32 },
+    /// StateChanged signal of the device.
+    Device { state: u32, reason: u32 },
+}
+
+/// Wait for an active connection to reach Activated or fail. Reports the
+/// phases the device goes through to `on_phase`.
 async fn wait_for_activation(
     connection: &zbus::Connection,
     active_path: &zbus::zvariant::OwnedObjectPath,
-) -> Result<(), String> {
+    device_path: &zbus::zvariant::ObjectPath<'_>,
+    on_phase: &mut impl FnMut(ConnectPhase),
+) -> Result<(), ActivationError> {
+    use iced::futures::future::{self, Either};
+    use iced::futures::stream::{self, StreamExt};
+
     let ac = ActiveConnectionProxy::builder(connection)
         .path(active_path)
-        .map_err(|e| format!("Invalid active connection path: {e}"))?
+        .map_err(|e| ActivationError::DBus(format!("Invalid active connection path: {e}")))?
         .build()
         .await
-        .map_err(|e| format!("Failed to create active connection proxy: {e}"))?;
+        .map_err(|e| {
+            ActivationError::DBus(format!("Failed to create active connection proxy: {e}"))
+        })?;
+
+    // The StateChanged signal carries the reason along with the state, which
+    // is what tells an authentication failure apart from other failures.
+    let connection_events = ac
+        .receive_activation_state_changed()
+        .await
+        .map_err(|e| ActivationError::DBus(format!("Failed to watch activation: {e}")))?
+        .filter_map(|signal| async move {
+            let args = signal.args().ok()?;
+            Some(ActivationEvent::Connection {
+                state: args.state,
+                reason: args.reason,
+            })
+        });
+
+    // Used to report progress, and for the reason of a failure: activation is
+    // tracked on `ac`
+    let device = match DeviceProxy::builder(connection).path(device_path) {
+        Ok(builder) => builder.build().await.ok(),
+        Err(_) => None,
+    };
+    let device_events = match &device {
+        // Not in `DeviceProxy`: the types zbus generates for it would clash
+        // with the ones of `ActiveConnection.StateChanged`
+        Some(device) => match device.inner().receive_signal("StateChanged").await {
+            Ok(signals) => signals
+                .filter_map(|message| async move {
+                    let (state, _old_state, reason): (u32, u32, u32) =
+                        message.body().deserialize().ok()?;
+                    Some(ActivationEvent::Device { state, reason })
+                })
+                .boxed(),
+            Err(_) => stream::empty().boxed(),
+        },
+        None => stream::empty().boxed(),
+    };
+
+    // The activation may have finished before we subscribed
+    match ac.state().await {
+        Ok(NM_ACTIVE_CONNECTION_STATE_ACTIVATED) => {
+            on_phase(ConnectPhase::Connected);
+            return Ok(());
+        }
+        Ok(NM_ACTIVE_CONNECTION_STATE_DEACTIVATING | NM_ACTIVE_CONNECTION_STATE_DEACTIVATED) => {
+            return Err(ActivationError::Failed);
+        }
+        // NM sometimes replaces the active connection object right after
+        // creating it. Follow what's active on the device instead.
+        Err(e) if is_unknown_object(&e) => {
+            return match active_wifi_path(connection, device_path.as_str()).await {
+                Some(path) if path != *active_path => {
+                    Box::pin(wait_for_activation(
+                        connection,
+                        &path,
+                        device_path,
+                        on_phase,
+                    ))
+                    .await
+                }
+                _ => Err(ActivationError::Failed),
+            };
+        }
+        _ => {}
+    }
 
-    for _ in 0..15 {
-        match ac.state().await {
-            Ok(NM_ACTIVE_CONNECTION_STATE_ACTIVATED) => return Ok(()),
-            Ok(
-                NM_ACTIVE_CONNECTION_STATE_DEACTIVATING | NM_ACTIVE_CONNECTION_STATE_DEACTIVATED,
-            ) => {
-                return Err("Connection failed (wrong password?)".to_string());
+    let mut events = std::pin::pin!(stream::select(connection_events, device_events));
+    let mut timeout = async_io::Timer::after(ACTIVATION_TIMEOUT);
+    let mut phase = ConnectPhase::Starting;
+    let mut device_reason = None;
+
+    loop {
+        let event = match future::select(events.next(), &mut timeout).await {
+            Either::Left((Some(event), _)) => event,
+            Either::Left((None, _)) => return Err(ActivationError::Failed),
+            Either::Right(_) => return Err(ActivationError::TimedOut),
+        };
+
+        match event {
+            ActivationEvent::Device {
+                state: NM_DEVICE_STATE_FAILED,
+                reason,
+            } if is_device_auth_failure(reason) => return Err(ActivationError::AuthFailed),
+            ActivationEvent::Device { state, reason } => {
+                device_reason = Some(reason);
+                if let Some(new_phase) = ConnectPhase::from_device_state(state)
+                    && new_phase != phase
+                {
+                    phase = new_phase;
+                    on_phase(phase);
+                }
             }
-            Ok(NM_ACTIVE_CONNECTION_STATE_ACTIVATING) => {} // keep waiting
-            Ok(_) | Err(_) => {
-                return Err("Connection failed".to_string());
+            ActivationEvent::Connection {
+                state: NM_ACTIVE_CONNECTION_STATE_ACTIVATED,
+                ..
+            } => {
+                if phase != ConnectPhase::Connected {
+                    on_phase(ConnectPhase::Connected);
+                }
+                return Ok(());
             }
+            ActivationEvent::Connection {
+                state:
+                    NM_ACTIVE_CONNECTION_STATE_DEACTIVATING | NM_ACTIVE_CONNECTION_STATE_DEACTIVATED,
+                reason,
+            } => {
+                return Err(deactivation_error(reason, device_reason));
+            }
+            ActivationEvent::Connection {
+                state: NM_ACTIVE_CONNECTION_STATE_ACTIVATING,
+                ..
+            } => {} // keep waiting
+            ActivationEvent::Connection { .. } => {}
         }
-        async_io::Timer::after(std::time::Duration::from_secs(1)).await;
     }
+}
+
+/// Whether a D-Bus error means the object doesn't exist (anymore).
+fn is_unknown_object(e: &zbus::Error) -> bool {
+    match e {
+        zbus::Error::MethodError(name, _, _) => matches!(
+            name.as_str(),
+            "org.freedesktop.DBus.Error.UnknownObject" | "org.freedesktop.DBus.Error.UnknownMethod"
+        ),
+        zbus::Error::FDO(e) => matches!(
+            **e,
+            zbus::fdo::Error::UnknownObject(_) | zbus::fdo::Error::UnknownMethod(_)
+        ),
+        _ => false,
+    }
+}
+
+/// Path of the WiFi connection active (or activating) on a device.
+async fn active_wifi_path(
+    connection: &zbus::Connection,
+    device_path: &str,
+) -> Option<zbus::zvariant::OwnedObjectPath> {
+    let nm = NetworkManagerProxy::new(connection).await.ok()?;
+    let ac = active_wifi_on_device(&nm, connection, device_path)
+        .await
+        .ok()??;
+    Some(ac.inner().path().to_owned().into())
+}
+
+/// Delete a saved connection profile, best-effort.
+async fn delete_profile(connection: &zbus::Connection, path: &zbus::zvariant::OwnedObjectPath) {
+    let Ok(builder) = SettingsConnectionProxy::builder(connection).path(path) else {
+        return;
+    };
+    if let Ok(conn_proxy) = builder.build().await {
+        let _ = conn_proxy.delete().await;
+    }
+}
+
+/// A raw WEP key: 5 or 13 ASCII characters, or 10 or 26 hex digits.
+fn is_wep_key(password: &str) -> bool {
+    let hex = password.chars().all(|c| c.is_ascii_hexdigit());
+    match password.len() {
+        5 | 13 => password.is_ascii(),
+        10 | 26 => hex,
+        _ => false,
+    }
+}
+
+/// Check that `password` is acceptable for a new profile with the given key
+/// management before handing it to NM, which would only reject it after
+/// trying to connect.
+pub fn check_password(key_mgmt: KeyMgmt, password: &str) -> Result<(), String> {
+    match key_mgmt {
+        KeyMgmt::None | KeyMgmt::Auto => Ok(()),
+        _ if password.is_empty() => Err("Enter the password".to_string()),
+        KeyMgmt::WpaPsk | KeyMgmt::Sae => {
+            let len = password.len();
+            let hex_key = len == 64 && password.chars().all(|c| c.is_ascii_hexdigit());
+            if (8..=63).contains(&len) || hex_key {
+                Ok(())
+            } else {
+                Err("WPA passwords are 8 to 63 characters".to_string())
+            }
+        }
+        KeyMgmt::Wep if is_wep_key(password) => Ok(()),
+        KeyMgmt::Wep => Err("WEP keys are 5 or 13 characters, or 10 or 26 hex digits".to_string()),
+        KeyMgmt::WpaEap => Ok(()),
+    }
+}
+
+/// Advanced options for new connection profiles. Saved profiles are activated
+/// as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ConnectOptions {
+    /// Overrides the key management derived from the network's detected
+    /// security.
+    pub key_mgmt: KeyMgmt,
+    /// Pin the profile to the interface with this name, so it never
+    /// activates on other adapters.
+    pub interface_name: Option<String>,
+    /// Delete the saved profile for the SSID and create a new one, instead of
+    /// activating it.
+    pub replace_saved: bool,
+    /// Delete the new profile when NM rejects the password. Otherwise it's
+    /// kept and the attempt fails with `ConnectError::SavedSecretsRejected`.
+    pub forget_on_auth_failure: bool,
+    /// Username for Enterprise (802.1X) networks.
+    pub identity: String,
+    /// Identity sent unencrypted before the PEAP tunnel is set up, for
+    /// Enterprise networks. The real identity is used when empty.
+    pub anonymous_identity: String,
+    /// Mark the new profile metered, so background traffic is held back.
+    pub metered: bool,
+    /// Lock the profile to the access point with this BSSID, so NM doesn't
+    /// roam away from it. Saved profiles are updated to it.
+    pub bssid: Option<String>,
+    /// IPv4 settings of the new profile. DHCP unless `ip4.manual`, possibly
+    /// with custom DNS servers.
+    pub ip4: Ip4Settings,
+    /// Mark the new profile hidden, so NM probes for the SSID instead of
+    /// waiting to see it in a scan. For networks joined by name.
+    pub hidden: bool,
+}
+
+impl ConnectOptions {
+    /// The key management a new profile for `network` gets.
+    pub fn key_mgmt_for(&self, network: &Network) -> KeyMgmt {
+        match self.key_mgmt {
+            KeyMgmt::Auto => KeyMgmt::for_security(&network.security),
+            key_mgmt => key_mgmt,
+        }
+    }
+}
+
+/// Settings for a new connection profile for `network`.
+fn new_profile_settings<'a>(
+    network: &'a Network,
+    password: &'a str,
+    options: &ConnectOptions,
+) -> Result<HashMap<&'static str, HashMap<&'static str, zbus::zvariant::Value<'a>>>, String> {
+    let mut settings: HashMap<&str, HashMap<&str, zbus::zvariant::Value<'_>>> = HashMap::new();
+
+    let mut conn_section: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
+    conn_section.insert("type", "802-11-wireless".into());
+    conn_section.insert("id", network.ssid.as_str().into());
+    if let Some(interface_name) = &options.interface_name {
+        conn_section.insert("interface-name", interface_name.clone().into());
+    }
+    if options.metered {
+        conn_section.insert("metered", NM_METERED_YES.into());
+    }
+    settings.insert("connection", conn_section);
+
+    let mut wireless_section: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
+    wireless_section.insert(
+        "ssid",
+        zbus::zvariant::Value::from(network.ssid_bytes.as_slice()),
+    );
+    if let Some(bssid) = &options.bssid {
+        wireless_section.insert("bssid", parse_bssid(bssid)?.into());
+    }
+    wireless_section.insert("mode", "infrastructure".into());
+    if options.hidden {
+        wireless_section.insert("hidden", true.into());
+    }
+    settings.insert("802-11-wireless", wireless_section);
+
+    let key_mgmt = options.key_mgmt_for(network);
+    match key_mgmt {
+        KeyMgmt::None | KeyMgmt::Auto => {}
+        KeyMgmt::WpaEap => {
+            if options.identity.is_empty() {
+                return Err("Enterprise networks need a username".to_string());
+            }
+            let mut security_section: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
+            security_section.insert("key-mgmt", key_mgmt.to_string().into());
+            settings.insert("802-11-wireless-security", security_section);
+
+            let mut eap_section: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
+            eap_section.insert("eap", vec!["peap"].into());
+            // What PEAP networks use in practice
+            eap_section.insert("phase2-auth", "mschapv2".into());
+            eap_section.insert("identity", options.identity.clone().into());
+            if !options.anonymous_identity.is_empty() {
+                eap_section.insert(
+                    "anonymous-identity",
+                    options.anonymous_identity.clone().into(),
+                );
+            }
+            eap_section.insert("password", password.into());
+            settings.insert("802-1x", eap_section);
+        }
+        KeyMgmt::WpaPsk | KeyMgmt::Sae => {
+            let mut security_section: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
+            security_section.insert("key-mgmt", key_mgmt.to_string().into());
+            security_section.insert("psk", password.into());
+            settings.insert("802-11-wireless-security", security_section);
+        }
+        KeyMgmt::Wep => {
+            let mut security_section: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
+            security_section.insert("key-mgmt", "none".into());
+            security_section.insert("wep-key0", password.into());
+            // NM_WEP_KEY_TYPE_KEY = 1, NM_WEP_KEY_TYPE_PASSPHRASE = 2
+            let key_type: u32 = if is_wep_key(password) { 1 } else { 2 };
+            security_section.insert("wep-key-type", key_type.into());
+            settings.insert("802-11-wireless-security", security_section);
+        }
+    }
+
+    // No `ipv4` section means DHCP
+    if options.ip4 != Ip4Settings::default() {
+        settings.insert("ipv4", options.ip4.section()?);
+    }
+
+    Ok(settings)
+}
 
-    Err("Connection timed out".to_string())
+/// Activate a saved connection profile and wait for it to come up.
+async fn activate_saved(
+    connection: &zbus::Connection,
+    nm: &NetworkManagerProxy<'_>,
+    saved_path: &zbus::zvariant::OwnedObjectPath,
+    device_path: &zbus::zvariant::ObjectPath<'_>,
+    ap_path: &zbus::zvariant::ObjectPath<'_>,
+    on_phase: &mut impl FnMut(ConnectPhase),
+) -> Result<(), ConnectError> {
+    let active_path = nm
+        .activate_connection(saved_path, device_path, ap_path)
+        .await
+        .map_err(|e| connect_error_message(&e))?;
+    match wait_for_activation(connection, &active_path, device_path, on_phase).await {
+        Ok(()) => Ok(()),
+        // Keep the profile, the user decides whether to replace it
+        Err(ActivationError::AuthFailed) => Err(ConnectError::SavedSecretsRejected),
+        Err(e) => Err(ConnectError::Other(e.to_string())),
+    }
 }
 
-pub async fn connect(network: Network, password: String) -> Result<(), String> {
-    let connection = zbus::Connection::system()
+/// Re-activate the saved profile for `network`. Unlike `connect`, never
+/// creates a profile.
+pub async fn reconnect(
+    network: Network,
+    mut on_phase: impl FnMut(ConnectPhase),
+) -> Result<(), ConnectError> {
+    let connection = bus()
         .await
         .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
 
@@ -318,86 +2229,131 @@ pub async fn connect(network: Network, password: String) -> Result<(), String> {
     let ap_path = zbus::zvariant::ObjectPath::try_from(network.ap_path.as_str())
         .map_err(|e| format!("Invalid AP path: {e}"))?;
 
-    // Check if there's a saved connection profile for this SSID
-    if let Some(saved_path) = find_saved_connection(&connection, &network.ssid).await? {
-        let saved_obj = zbus::zvariant::ObjectPath::try_from(saved_path.as_str())
-            .map_err(|e| format!("Invalid saved connection path: {e}"))?;
-        let active_path = nm
-            .activate_connection(&saved_obj, &device_path, &ap_path)
-            .await
-            .map_err(|e| format!("Failed to connect: {e}"))?;
-        let result = wait_for_activation(&connection, &active_path).await;
-        if result.is_err() {
-            // Delete the saved profile so the user can retry with a new password
-            if let Ok(conn_proxy) = SettingsConnectionProxy::builder(&connection)
-                .path(saved_path.as_ref())
-                .unwrap()
-                .build()
-                .await
-            {
-                let _ = conn_proxy.delete().await;
-            }
-        }
-        return result;
+    let Some(saved_path) = find_saved_connection(&connection, &network.ssid_bytes).await? else {
+        return Err(format!("No saved profile for {}", network.ssid).into());
+    };
+    activate_saved(
+        &connection,
+        &nm,
+        &saved_path,
+        &device_path,
+        &ap_path,
+        &mut on_phase,
+    )
+    .await
+}
+
+/// How `connect` gets a profile for a network.
+#[derive(Debug, PartialEq, Eq)]
+enum ProfileChoice<'a> {
+    /// Activate the saved profile.
+    Reuse(&'a zbus::zvariant::OwnedObjectPath),
+    /// Delete the saved profile and create a new one.
+    Replace(&'a zbus::zvariant::OwnedObjectPath),
+    /// Nothing saved for the SSID, create a profile.
+    Create,
+}
+
+/// Saved profiles are reused whatever the security, so connecting again to
+/// an open network doesn't add a duplicate.
+fn choose_profile<'a>(
+    profiles: &'a [SavedProfile],
+    network: &Network,
+    options: &ConnectOptions,
+) -> ProfileChoice<'a> {
+    match find_profile(profiles, &network.ssid_bytes) {
+        Some(profile) if options.replace_saved => ProfileChoice::Replace(&profile.path),
+        Some(profile) => ProfileChoice::Reuse(&profile.path),
+        None => ProfileChoice::Create,
     }
+}
 
-    // No saved connection — build settings and create a new one
-    let mut settings: HashMap<&str, HashMap<&str, zbus::zvariant::Value<'_>>> = HashMap::new();
+/// Connect to `network`. `options` are only used when a new profile is
+/// created. Activation progress is reported to `on_phase`.
+pub async fn connect(
+    network: Network,
+    password: String,
+    options: ConnectOptions,
+    mut on_phase: impl FnMut(ConnectPhase),
+) -> Result<(), ConnectError> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
 
-    let mut conn_section: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
-    conn_section.insert("type", "802-11-wireless".into());
-    conn_section.insert("id", network.ssid.as_str().into());
-    settings.insert("connection", conn_section);
+    let nm = NetworkManagerProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to create NetworkManager proxy: {e}"))?;
 
-    let mut wireless_section: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
-    wireless_section.insert("ssid", zbus::zvariant::Value::from(network.ssid.as_bytes()));
-    wireless_section.insert("mode", "infrastructure".into());
-    settings.insert("802-11-wireless", wireless_section);
+    let device_path = zbus::zvariant::ObjectPath::try_from(network.device_path.as_str())
+        .map_err(|e| format!("Invalid device path: {e}"))?;
+    // Activate on the pinned access point rather than the strongest one
+    let pinned_ap = options.bssid.as_ref().and_then(|bssid| {
+        network
+            .access_points
+            .iter()
+            .find(|ap| ap.bssid == *bssid && ap.path != "/")
+    });
+    let ap_path = pinned_ap.map_or(network.ap_path.as_str(), |ap| ap.path.as_str());
+    let ap_path = zbus::zvariant::ObjectPath::try_from(ap_path)
+        .map_err(|e| format!("Invalid AP path: {e}"))?;
 
-    if network.security != "Open" {
-        let mut security_section: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
-        let key_mgmt = if network.security == "WPA3" {
-            "sae"
-        } else {
-            "wpa-psk"
-        };
-        security_section.insert("key-mgmt", key_mgmt.into());
-        security_section.insert("psk", password.as_str().into());
-        settings.insert("802-11-wireless-security", security_section);
+    let profiles = saved_profiles(&connection).await?;
+    match choose_profile(&profiles, &network, &options) {
+        ProfileChoice::Reuse(saved_path) => {
+            if let Some(bssid) = &options.bssid {
+                let profile = SettingsConnectionProxy::builder(&connection)
+                    .path(saved_path)
+                    .map_err(|e| format!("Invalid connection path: {e}"))?
+                    .build()
+                    .await
+                    .map_err(|e| format!("Failed to create connection proxy: {e}"))?;
+                pin_bssid(&profile, &network.ssid, Some(bssid)).await?;
+            }
+            return activate_saved(
+                &connection,
+                &nm,
+                saved_path,
+                &device_path,
+                &ap_path,
+                &mut on_phase,
+            )
+            .await;
+        }
+        ProfileChoice::Replace(saved_path) => delete_profile(&connection, saved_path).await,
+        ProfileChoice::Create => {}
     }
 
+    let settings = new_profile_settings(&network, &password, &options)?;
+
     let (active_path, settings_path) = nm
         .add_and_activate_connection(settings, &device_path, &ap_path)
         .await
-        .map_err(|e| format!("Failed to connect: {e}"))?;
-
-    let result = wait_for_activation(&connection, &active_path).await;
-    if result.is_err() {
-        // Delete the saved profile so the user can retry with a new password
-        if let Ok(conn_proxy) = SettingsConnectionProxy::builder(&connection)
-            .path(settings_path.as_ref())
-            .unwrap()
-            .build()
-            .await
-        {
-            let _ = conn_proxy.delete().await;
+        .map_err(|e| connect_error_message(&e))?;
+
+    match wait_for_activation(&connection, &active_path, &device_path, &mut on_phase).await {
+        Ok(()) => Ok(()),
+        Err(ActivationError::AuthFailed) if options.forget_on_auth_failure => {
+            // Delete the new profile so the user can retry with a new password
+            delete_profile(&connection, &settings_path).await;
+            Err(ConnectError::PasswordRejected)
         }
+        Err(ActivationError::AuthFailed) => Err(ConnectError::SavedSecretsRejected),
+        Err(e) => Err(ConnectError::Other(e.to_string())),
     }
-    result
 }
 
-/// Check if the given device has an active WiFi connection.
-async fn has_active_wifi_on_device(
+/// The active WiFi connection on a device, if any.
+async fn active_wifi_on_device(
     nm: &NetworkManagerProxy<'_>,
     connection: &zbus::Connection,
     device_path: &str,
-) -> Result<bool, String> {
+) -> Result<Option<ActiveConnectionProxy<'static>>, String> {
     let active_connections = nm
         .active_connections()
         .await
         .map_err(|e| format!("Failed to get active connections: {e}"))?;
 
-    for path in &active_connections {
+    for path in active_connections {
         let ac = ActiveConnectionProxy::builder(connection)
             .path(path)
             .map_err(|e| format!("Invalid active connection path: {e}"))?
@@ -408,16 +2364,57 @@ async fn has_active_wifi_on_device(
         if ac.connection_type().await.unwrap_or_default() == "802-11-wireless" {
             let devices = ac.devices().await.unwrap_or_default();
             if devices.iter().any(|d| d.as_str() == device_path) {
-                return Ok(true);
+                return Ok(Some(ac));
             }
         }
     }
 
-    Ok(false)
+    Ok(None)
+}
+
+/// SSID of the WiFi connection active on a device, without scanning.
+pub async fn current_connection(device_path: &str) -> Result<Option<String>, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+
+    let nm = NetworkManagerProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to create NetworkManager proxy: {e}"))?;
+
+    let Some(ac) = active_wifi_on_device(&nm, &connection, device_path).await? else {
+        return Ok(None);
+    };
+    let profile_path = ac
+        .connection()
+        .await
+        .map_err(|e| format!("Failed to get the active profile: {e}"))?;
+    let settings = SettingsConnectionProxy::builder(&connection)
+        .path(profile_path)
+        .map_err(|e| format!("Invalid connection path: {e}"))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to create connection proxy: {e}"))?
+        .get_settings()
+        .await
+        .map_err(|e| format!("Failed to get connection settings: {e}"))?;
+    Ok(get_wifi_ssid(&settings))
+}
+
+/// A connection NM currently has active, of any type.
+#[derive(Debug, Clone)]
+pub struct ActiveConnection {
+    pub path: String,
+    /// Name of the connection profile, the SSID for WiFi profiles by default.
+    pub id: String,
+    /// NM connection type, e.g. "802-11-wireless", "vpn", "wireguard".
+    pub connection_type: String,
+    /// Interfaces of the devices the connection is active on.
+    pub interfaces: Vec<String>,
 }
 
-pub async fn disconnect(device_path: &str) -> Result<(), String> {
-    let connection = zbus::Connection::system()
+pub async fn list_active_connections() -> Result<Vec<ActiveConnection>, String> {
+    let connection = bus()
         .await
         .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
 
@@ -430,7 +2427,7 @@ pub async fn disconnect(device_path: &str) -> Result<(), String> {
         .await
         .map_err(|e| format!("Failed to get active connections: {e}"))?;
 
-    let mut found = false;
+    let mut result = Vec::new();
     for path in &active_connections {
         let ac = ActiveConnectionProxy::builder(&connection)
             .path(path)
@@ -439,26 +2436,225 @@ pub async fn disconnect(device_path: &str) -> Result<(), String> {
             .await
             .map_err(|e| format!("Failed to create active connection proxy: {e}"))?;
 
-        if ac.connection_type().await.unwrap_or_default() == "802-11-wireless" {
-            let devices = ac.devices().await.unwrap_or_default();
-            if devices.iter().any(|d| d.as_str() == device_path) {
-                nm.deactivate_connection(path)
-                    .await
-                    .map_err(|e| format!("Failed to disconnect: {e}"))?;
-                found = true;
-                break;
+        let mut interfaces = Vec::new();
+        for device_path in ac.devices().await.unwrap_or_default() {
+            let Ok(builder) = DeviceProxy::builder(&connection).path(&device_path) else {
+                continue;
+            };
+            if let Ok(device) = builder.build().await
+                && let Ok(interface) = device.interface().await
+            {
+                interfaces.push(interface);
             }
         }
+
+        result.push(ActiveConnection {
+            path: path.to_string(),
+            id: ac.id().await.unwrap_or_default(),
+            connection_type: ac.connection_type().await.unwrap_or_default(),
+            interfaces,
+        });
+    }
+
+    Ok(result)
+}
+
+/// Deactivate the active connection at `active_path`.
+pub async fn deactivate(active_path: String) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+
+    let nm = NetworkManagerProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to create NetworkManager proxy: {e}"))?;
+
+    let path = zbus::zvariant::ObjectPath::try_from(active_path.as_str())
+        .map_err(|e| format!("Invalid active connection path: {e}"))?;
+    nm.deactivate_connection(&path)
+        .await
+        .map_err(|e| format!("Failed to deactivate connection: {e}"))
+}
+
+/// A saved WireGuard profile.
+#[derive(Debug, Clone)]
+pub struct WireGuard {
+    /// Path of the settings profile.
+    pub path: String,
+    pub id: String,
+    /// Path of the active connection, if the tunnel is up.
+    pub active_path: Option<String>,
+}
+
+pub async fn list_wireguard() -> Result<Vec<WireGuard>, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+
+    let nm = NetworkManagerProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to create NetworkManager proxy: {e}"))?;
+    let settings = SettingsProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to create Settings proxy: {e}"))?;
+
+    // Profile path -> active connection path
+    let mut active = HashMap::new();
+    for path in nm.active_connections().await.unwrap_or_default() {
+        let Ok(builder) = ActiveConnectionProxy::builder(&connection).path(&path) else {
+            continue;
+        };
+        if let Ok(ac) = builder.build().await
+            && ac.connection_type().await.unwrap_or_default() == "wireguard"
+            && let Ok(profile) = ac.connection().await
+        {
+            active.insert(profile.to_string(), path.to_string());
+        }
+    }
+
+    let mut tunnels = Vec::new();
+    for path in settings
+        .list_connections()
+        .await
+        .map_err(|e| format!("Failed to list connections: {e}"))?
+    {
+        let Ok(builder) = SettingsConnectionProxy::builder(&connection).path(&path) else {
+            continue;
+        };
+        let Ok(s) = builder.build().await else {
+            continue;
+        };
+        let Ok(s) = s.get_settings().await else {
+            continue;
+        };
+        let Some(conn) = s.get("connection") else {
+            continue;
+        };
+        let conn_type: &str = conn
+            .get("type")
+            .and_then(|v| v.try_into().ok())
+            .unwrap_or_default();
+        if conn_type != "wireguard" {
+            continue;
+        }
+        let id: &str = conn
+            .get("id")
+            .and_then(|v| v.try_into().ok())
+            .unwrap_or_default();
+        tunnels.push(WireGuard {
+            path: path.to_string(),
+            id: id.to_string(),
+            active_path: active.remove(path.as_str()),
+        });
+    }
+
+    Ok(tunnels)
+}
+
+/// Bring a WireGuard tunnel up or down.
+pub async fn set_wireguard(tunnel: WireGuard, up: bool) -> Result<(), String> {
+    if !up {
+        return match tunnel.active_path {
+            Some(active_path) => deactivate(active_path).await,
+            None => Ok(()),
+        };
     }
 
-    if !found {
-        return Err("No active WiFi connection found on this device".to_string());
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+
+    let nm = NetworkManagerProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to create NetworkManager proxy: {e}"))?;
+
+    let profile = zbus::zvariant::ObjectPath::try_from(tunnel.path.as_str())
+        .map_err(|e| format!("Invalid connection path: {e}"))?;
+    // No device or specific object, NM creates the tunnel's interface
+    let none = zbus::zvariant::ObjectPath::from_static_str_unchecked("/");
+    nm.activate_connection(&profile, &none, &none)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to activate {}: {e}", tunnel.id))
+}
+
+/// SSID of the profile an active connection was activated from.
+async fn active_connection_ssid(
+    connection: &zbus::Connection,
+    ac: &ActiveConnectionProxy<'_>,
+) -> Option<String> {
+    let profile = ac.connection().await.ok()?;
+    let settings = SettingsConnectionProxy::builder(connection)
+        .path(&profile)
+        .ok()?
+        .build()
+        .await
+        .ok()?
+        .get_settings()
+        .await
+        .ok()?;
+    get_wifi_ssid(&settings)
+}
+
+/// Deactivate the WiFi connection to `ssid` on the device, or the first WiFi
+/// connection on it when `ssid` is `None`.
+pub async fn disconnect(device_path: &str, ssid: Option<&str>) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+
+    let nm = NetworkManagerProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to create NetworkManager proxy: {e}"))?;
+
+    let active_connections = nm
+        .active_connections()
+        .await
+        .map_err(|e| format!("Failed to get active connections: {e}"))?;
+
+    let mut found = None;
+    for path in &active_connections {
+        let ac = ActiveConnectionProxy::builder(&connection)
+            .path(path)
+            .map_err(|e| format!("Invalid active connection path: {e}"))?
+            .build()
+            .await
+            .map_err(|e| format!("Failed to create active connection proxy: {e}"))?;
+
+        if ac.connection_type().await.unwrap_or_default() != "802-11-wireless" {
+            continue;
+        }
+        let devices = ac.devices().await.unwrap_or_default();
+        if !devices.iter().any(|d| d.as_str() == device_path) {
+            continue;
+        }
+        if let Some(ssid) = ssid
+            && active_connection_ssid(&connection, &ac).await.as_deref() != Some(ssid)
+        {
+            continue;
+        }
+        nm.deactivate_connection(path)
+            .await
+            .map_err(|e| format!("Failed to disconnect: {e}"))?;
+        found = Some(path);
+        break;
     }
 
-    // Poll until NetworkManager confirms no active WiFi on this device
+    let Some(deactivated) = found else {
+        return Err(match ssid {
+            Some(ssid) => format!("Not connected to {ssid} on this device"),
+            None => "No active WiFi connection found on this device".to_string(),
+        });
+    };
+
+    // Poll until NetworkManager drops the connection
     for _ in 0..10 {
         async_io::Timer::after(std::time::Duration::from_secs(1)).await;
-        if !has_active_wifi_on_device(&nm, &connection, device_path).await? {
+        let active = nm
+            .active_connections()
+            .await
+            .map_err(|e| format!("Failed to get active connections: {e}"))?;
+        if !active.contains(deactivated) {
             return Ok(());
         }
     }
@@ -466,8 +2662,106 @@ pub async fn disconnect(device_path: &str) -> Result<(), String> {
     Err("Disconnect timed out".to_string())
 }
 
+async fn device_proxy<'a>(
+    connection: &zbus::Connection,
+    device_path: &'a str,
+) -> Result<DeviceProxy<'a>, String> {
+    DeviceProxy::builder(connection)
+        .path(device_path)
+        .map_err(|e| format!("Invalid device path: {e}"))?
+        .build()
+        .await
+        .map_err(|e| format!("Failed to create device proxy: {e}"))
+}
+
+/// Disconnect the device, whatever connection it has active. NM won't
+/// autoconnect it again until asked to connect.
+pub async fn disconnect_device(device_path: String) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    device_proxy(&connection, &device_path)
+        .await?
+        .disconnect()
+        .await
+        .map_err(|e| format!("Failed to disconnect {device_path}: {e}"))
+}
+
+/// Hand the device over to NM, or release it to other tools.
+pub async fn set_managed(device_path: String, managed: bool) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    device_proxy(&connection, &device_path)
+        .await?
+        .set_managed(managed)
+        .await
+        .map_err(|e| format!("Failed to change managed state: {e}"))
+}
+
+/// Route metric given to the device made the default with `make_default_route`.
+/// NM's defaults for WiFi are 600 and up.
+const DEFAULT_ROUTE_METRIC: i64 = 50;
+
+/// Make the device's connection preferred for the default route by lowering
+/// its route metrics. Only changes the active connection, the saved profile
+/// keeps its settings.
+pub async fn make_default_route(device_path: String) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let device = device_proxy(&connection, &device_path).await?;
+
+    let (mut settings, version_id) = device
+        .get_applied_connection(0)
+        .await
+        .map_err(|e| format!("Device has no active connection: {e}"))?;
+    for section in ["ipv4", "ipv6"] {
+        settings.entry(section.to_string()).or_default().insert(
+            "route-metric".to_string(),
+            zbus::zvariant::OwnedValue::from(DEFAULT_ROUTE_METRIC),
+        );
+    }
+
+    device
+        .reapply(settings, version_id, 0)
+        .await
+        .map_err(|e| format!("Failed to apply route metric: {e}"))
+}
+
+/// Reapply the device's active connection unchanged. NM restarts IP
+/// configuration (e.g. renews the DHCP lease) without tearing the link down.
+pub async fn reassociate(device_path: String) -> Result<(), String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let device = device_proxy(&connection, &device_path).await?;
+
+    let (settings, version_id) = device
+        .get_applied_connection(0)
+        .await
+        .map_err(|e| format!("Device has no active connection: {e}"))?;
+    device
+        .reapply(settings, version_id, 0)
+        .await
+        .map_err(|e| format!("Failed to reassociate: {e}"))
+}
+
+/// NetworkManager's version, e.g. "1.48.10".
+pub async fn version() -> Result<String, String> {
+    let connection = bus()
+        .await
+        .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
+    let nm = NetworkManagerProxy::new(&connection)
+        .await
+        .map_err(|e| format!("Failed to create NetworkManager proxy: {e}"))?;
+    nm.version()
+        .await
+        .map_err(|e| format!("Failed to get NetworkManager version: {e}"))
+}
+
 pub async fn get_wifi_enabled() -> Result<bool, String> {
-    let connection = zbus::Connection::system()
+    let connection = bus()
         .await
         .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
     let nm = NetworkManagerProxy::new(&connection)
@@ -479,7 +2773,7 @@ pub async fn get_wifi_enabled() -> Result<bool, String> {
 }
 
 pub async fn set_wifi_enabled(enabled: bool) -> Result<bool, String> {
-    let connection = zbus::Connection::system()
+    let connection = bus()
         .await
         .map_err(|e| format!("Failed to connect to system D-Bus: {e}"))?;
     let nm = NetworkManagerProxy::new(&connection)
@@ -492,3 +2786,359 @@ pub async fn set_wifi_enabled(enabled: bool) -> Result<bool, String> {
         .await
         .map_err(|e| format!("Failed to get WiFi state: {e}"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+    fn wifi_settings(
+        ssid: &str,
+        connection: Vec<(&str, OwnedValue)>,
+    ) -> HashMap<String, HashMap<String, OwnedValue>> {
+        let mut conn_section: HashMap<String, OwnedValue> = connection
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        conn_section.insert(
+            "type".to_string(),
+            zbus::zvariant::Str::from("802-11-wireless").into(),
+        );
+        let mut wireless_section = HashMap::new();
+        wireless_section.insert(
+            "ssid".to_string(),
+            OwnedValue::try_from(zbus::zvariant::Value::from(ssid.as_bytes())).unwrap(),
+        );
+        let mut s = HashMap::new();
+        s.insert("connection".to_string(), conn_section);
+        s.insert("802-11-wireless".to_string(), wireless_section);
+        s
+    }
+
+    fn path(p: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(p).unwrap()
+    }
+
+    fn network(ssid: &[u8], security: &str) -> Network {
+        Network {
+            ssid: String::from_utf8_lossy(ssid).to_string(),
+            ssid_bytes: ssid.to_vec(),
+            strength: 70,
+            security: security.to_string(),
+            is_connected: false,
+            is_primary: false,
+            is_saved: false,
+            autoconnect: None,
+            metered: false,
+            pinned_bssid: None,
+            ap_count: 1,
+            frequency: 2412,
+            bssid: String::new(),
+            access_points: Vec::new(),
+            ap_path: "/ap/1".to_string(),
+            device_path: "/dev/1".to_string(),
+        }
+    }
+
+    #[test]
+    fn find_profile_prefers_priority_then_recency() {
+        // Two profiles for the same SSID: the static-IP variant was used more
+        // recently than the original one.
+        let home = wifi_settings("home", vec![("timestamp", 1_000u64.into())]);
+        let home_static = wifi_settings("home", vec![("timestamp", 2_000u64.into())]);
+        assert_eq!(get_wifi_ssid(&home).as_deref(), Some("home"));
+        assert_eq!(get_wifi_ssid(&home_static).as_deref(), Some("home"));
+
+        let profiles = vec![
+            SavedProfile::from_settings(path("/1"), &home).unwrap(),
+            SavedProfile::from_settings(path("/2"), &home_static).unwrap(),
+        ];
+        assert_eq!(find_profile(&profiles, "home").unwrap().path.as_str(), "/2");
+
+        // A higher autoconnect priority wins over recency.
+        let home = wifi_settings(
+            "home",
+            vec![
+                ("timestamp", 1_000u64.into()),
+                ("autoconnect-priority", 10i32.into()),
+            ],
+        );
+        let profiles = vec![
+            SavedProfile::from_settings(path("/1"), &home).unwrap(),
+            SavedProfile::from_settings(path("/2"), &home_static).unwrap(),
+        ];
+        assert_eq!(find_profile(&profiles, "home").unwrap().path.as_str(), "/1");
+    }
+
+    #[test]
+    fn find_profile_ties_go_to_first() {
+        let a = wifi_settings("cafe", vec![]);
+        let b = wifi_settings("cafe", vec![]);
+        let profiles = vec![
+            SavedProfile::from_settings(path("/a"), &a).unwrap(),
+            SavedProfile::from_settings(path("/b"), &b).unwrap(),
+        ];
+        assert_eq!(find_profile(&profiles, "cafe").unwrap().path.as_str(), "/a");
+        assert!(find_profile(&profiles, "home").is_none());
+        assert!(find_profile(&[], "cafe").is_none());
+    }
+
+    /// The profile NM would save for a new profile `connect` creates.
+    fn saved_profile(network: &Network, path: OwnedObjectPath) -> SavedProfile {
+        let settings = new_profile_settings(network, "", &ConnectOptions::default()).unwrap();
+        let settings: HashMap<String, HashMap<String, OwnedValue>> = settings
+            .into_iter()
+            .map(|(section, values)| {
+                let values = values
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), OwnedValue::try_from(v).unwrap()))
+                    .collect();
+                (section.to_string(), values)
+            })
+            .collect();
+        SavedProfile::from_settings(path, &settings).unwrap()
+    }
+
+    #[test]
+    fn reconnecting_to_open_network_reuses_profile() {
+        let network = network(b"cafe", "Open");
+        let options = ConnectOptions::default();
+        assert_eq!(
+            choose_profile(&[], &network, &options),
+            ProfileChoice::Create
+        );
+
+        // The profile created the first time is picked the second time
+        let profiles = [saved_profile(&network, path("/1"))];
+        assert_eq!(
+            choose_profile(&profiles, &network, &options),
+            ProfileChoice::Reuse(&path("/1"))
+        );
+
+        let replace = ConnectOptions {
+            replace_saved: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            choose_profile(&profiles, &network, &replace),
+            ProfileChoice::Replace(&path("/1"))
+        );
+    }
+
+    #[test]
+    fn batched_fetches_keep_the_order() {
+        // Later access points answer first
+        let fetch = |i: u64| async move {
+            async_io::Timer::after(std::time::Duration::from_millis(50 - i)).await;
+            Network {
+                strength: (i * 37 % 100) as u8,
+                is_connected: i == 33,
+                ap_path: format!("/ap/{i}"),
+                ..network(format!("net{}", i % 7).as_bytes(), "WPA2")
+            }
+        };
+        let mut batches = Vec::new();
+        let mut networks = async_io::block_on(fetch_in_batches(
+            (0..45).collect(),
+            SCAN_BATCH,
+            fetch,
+            |results: &[Network]| batches.push(results.len()),
+        ));
+        assert_eq!(batches, [20, 40]);
+        let paths: Vec<_> = networks.iter().map(|n| n.ap_path.clone()).collect();
+        let expected: Vec<_> = (0..45).map(|i| format!("/ap/{i}")).collect();
+        assert_eq!(paths, expected);
+
+        dedup_networks(&mut networks);
+        let rows: Vec<_> = networks
+            .iter()
+            .map(|n| (n.ssid.as_str(), n.strength))
+            .collect();
+        // Connected first, then the strongest AP of each other SSID
+        assert_eq!(
+            rows,
+            [
+                ("net5", 21),
+                ("net6", 99),
+                ("net1", 96),
+                ("net0", 95),
+                ("net2", 92),
+                ("net3", 88),
+                ("net4", 84),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_psk_is_an_auth_failure() {
+        // What NM sends for a wrong PSK: the device fails with NO_SECRETS,
+        // the active connection with DEVICE_DISCONNECTED
+        assert_eq!(deactivation_error(3, Some(7)), ActivationError::AuthFailed);
+        assert_eq!(deactivation_error(10, None), ActivationError::AuthFailed);
+        assert_eq!(
+            deactivation_error(3, Some(36)),
+            ActivationError::FailedBecause("the device disconnected")
+        );
+        assert_eq!(deactivation_error(1, None), ActivationError::Failed);
+    }
+
+    #[test]
+    fn password_lengths() {
+        assert!(check_password(KeyMgmt::WpaPsk, "short").is_err());
+        assert!(check_password(KeyMgmt::WpaPsk, "hunter22").is_ok());
+        assert!(check_password(KeyMgmt::Sae, &"x".repeat(64)).is_err());
+        assert!(check_password(KeyMgmt::WpaPsk, &"ab".repeat(32)).is_ok());
+        assert!(check_password(KeyMgmt::Wep, "abcde").is_ok());
+        assert!(check_password(KeyMgmt::Wep, "0123456789").is_ok());
+        assert!(check_password(KeyMgmt::Wep, "abcdef").is_err());
+        assert!(check_password(KeyMgmt::None, "").is_ok());
+        assert!(check_password(KeyMgmt::WpaEap, "").is_err());
+    }
+
+    #[test]
+    fn band_boundaries() {
+        assert_eq!(band_from_frequency(2412), Some("2.4 GHz"));
+        assert_eq!(band_from_frequency(2484), Some("2.4 GHz"));
+        // Channel 177, the highest 5 GHz channel
+        assert_eq!(band_from_frequency(5885), Some("5 GHz"));
+        assert_eq!(band_from_frequency(5924), Some("5 GHz"));
+        // 6 GHz starts at 5925 MHz, channel 2 is at 5935 and channel 1 at 5955
+        assert_eq!(band_from_frequency(5925), Some("6 GHz"));
+        assert_eq!(band_from_frequency(5935), Some("6 GHz"));
+        assert_eq!(band_from_frequency(5955), Some("6 GHz"));
+        // Channel 233, the highest 6 GHz channel
+        assert_eq!(band_from_frequency(7115), Some("6 GHz"));
+        assert_eq!(band_from_frequency(7125), Some("6 GHz"));
+        assert_eq!(band_from_frequency(7126), None);
+        assert_eq!(band_from_frequency(0), None);
+    }
+
+    #[test]
+    fn channel_numbers() {
+        assert_eq!(channel_from_frequency(2412), Some(1));
+        assert_eq!(channel_from_frequency(2472), Some(13));
+        assert_eq!(channel_from_frequency(2484), Some(14));
+        assert_eq!(channel_from_frequency(4920), Some(184));
+        assert_eq!(channel_from_frequency(5180), Some(36));
+        assert_eq!(channel_from_frequency(5885), Some(177));
+        assert_eq!(channel_from_frequency(5935), Some(2));
+        assert_eq!(channel_from_frequency(5955), Some(1));
+        assert_eq!(channel_from_frequency(7115), Some(233));
+        assert_eq!(channel_from_frequency(0), None);
+    }
+
+    #[test]
+    fn static_ip4_profile() {
+        let network = network(b"lab", "Open");
+        let mut options = ConnectOptions::default();
+        let settings = new_profile_settings(&network, "", &options).unwrap();
+        assert!(!settings.contains_key("ipv4"));
+
+        options.ip4 = Ip4Settings {
+            manual: true,
+            address: "10.0.0.5/16".to_string(),
+            gateway: "10.0.0.1".to_string(),
+            dns: "1.1.1.1".to_string(),
+        };
+        let settings = new_profile_settings(&network, "", &options).unwrap();
+        let ipv4 = &settings["ipv4"];
+        assert_eq!(ipv4["method"], "manual".into());
+        assert_eq!(ipv4["gateway"], "10.0.0.1".into());
+        assert_eq!(ipv4["dns"], vec![u32::from_ne_bytes([1, 1, 1, 1])].into());
+
+        options.ip4 = Ip4Settings {
+            dns: "1.1.1.1, 8.8.8.8".to_string(),
+            ..Ip4Settings::default()
+        };
+        let settings = new_profile_settings(&network, "", &options).unwrap();
+        let ipv4 = &settings["ipv4"];
+        assert_eq!(ipv4["method"], "auto".into());
+        assert_eq!(ipv4["ignore-auto-dns"], true.into());
+        let dns = vec![
+            u32::from_ne_bytes([1, 1, 1, 1]),
+            u32::from_ne_bytes([8, 8, 8, 8]),
+        ];
+        assert_eq!(ipv4["dns"], dns.into());
+
+        options.ip4.dns = "1.1.1.1, 8.8.8".to_string();
+        assert!(new_profile_settings(&network, "", &options).is_err());
+
+        options.ip4 = Ip4Settings {
+            manual: true,
+            ..Ip4Settings::default()
+        };
+        options.ip4.address = "10.0.0.5/33".to_string();
+        assert!(new_profile_settings(&network, "", &options).is_err());
+        options.ip4.address = "10.0.0/16".to_string();
+        assert!(new_profile_settings(&network, "", &options).is_err());
+    }
+
+    #[test]
+    fn non_utf8_ssids_match_by_bytes() {
+        // Latin-1 "café", and another SSID that displays the same
+        let with_ssid = |ssid: &[u8]| {
+            let mut settings = wifi_settings("", vec![]);
+            settings.get_mut("802-11-wireless").unwrap().insert(
+                "ssid".to_string(),
+                OwnedValue::try_from(zbus::zvariant::Value::from(ssid)).unwrap(),
+            );
+            settings
+        };
+        let profiles = vec![
+            SavedProfile::from_settings(path("/1"), &with_ssid(b"caf\xe9")).unwrap(),
+            SavedProfile::from_settings(path("/2"), &with_ssid(b"caf\xff")).unwrap(),
+        ];
+        assert_eq!(profiles[0].ssid, profiles[1].ssid);
+        assert_eq!(
+            find_profile(&profiles, b"caf\xe9").unwrap().path.as_str(),
+            "/1"
+        );
+        assert_eq!(
+            find_profile(&profiles, b"caf\xff").unwrap().path.as_str(),
+            "/2"
+        );
+
+        let network = network(b"caf\xe9", "Open");
+        assert_eq!(network.ssid, profiles[0].ssid);
+        let settings = new_profile_settings(&network, "", &ConnectOptions::default()).unwrap();
+        assert_eq!(
+            settings["802-11-wireless"]["ssid"],
+            zbus::zvariant::Value::from(&b"caf\xe9"[..])
+        );
+    }
+
+    #[test]
+    fn bssids() {
+        assert_eq!(
+            parse_bssid("00:1A:2b:3C:4d:FF"),
+            Ok(vec![0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0xff])
+        );
+        assert!(parse_bssid("00:1A:2B:3C:4D").is_err());
+        assert!(parse_bssid("00:1A:2B:3C:4D:FF:00").is_err());
+        assert!(parse_bssid("0:1A:2B:3C:4D:FF").is_err());
+        assert!(parse_bssid("+0:1A:2B:3C:4D:FF").is_err());
+        assert!(parse_bssid("").is_err());
+    }
+
+    #[test]
+    fn wifi_capability_names() {
+        // CCMP | WPA | RSN | AP | FREQ_VALID | FREQ_2GHZ | FREQ_5GHZ
+        let caps = WifiCapabilities {
+            flags: 0x8 | 0x10 | 0x20 | 0x40 | 0x100 | 0x200 | 0x400,
+            bitrate: 0,
+        };
+        assert_eq!(caps.names(), ["CCMP", "WPA", "RSN", "AP"]);
+        assert_eq!(caps.bands(), Some(vec!["2.4 GHz", "5 GHz"]));
+        assert_eq!(caps.max_band(), Some("5 GHz"));
+
+        // Band flags aren't meaningful without FREQ_VALID
+        let caps = WifiCapabilities {
+            flags: 0x20 | 0x200,
+            bitrate: 0,
+        };
+        assert_eq!(caps.names(), ["RSN"]);
+        assert_eq!(caps.bands(), None);
+        assert_eq!(caps.max_band(), None);
+    }
+}
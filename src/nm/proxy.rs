@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
 use zbus::proxy;
-use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+/// Connection settings as NM sends them: setting name -> property -> value.
+pub type ConnectionSettings = HashMap<String, HashMap<String, OwnedValue>>;
 
 #[proxy(
     interface = "org.freedesktop.NetworkManager",
@@ -15,6 +18,19 @@ pub trait NetworkManager {
     #[zbus(property)]
     fn active_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
 
+    #[zbus(property)]
+    fn version(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn primary_connection(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn primary_connection_type(&self) -> zbus::Result<String>;
+
+    /// `NMConnectivityState`, see `nm::Connectivity`.
+    #[zbus(property)]
+    fn connectivity(&self) -> zbus::Result<u32>;
+
     #[zbus(name = "DeactivateConnection")]
     fn deactivate_connection(
         &self,
@@ -60,6 +76,81 @@ pub trait Device {
 
     #[zbus(property)]
     fn interface(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn ip4_config(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn ip6_config(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn active_connection(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn managed(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn set_managed(&self, value: bool) -> zbus::Result<()>;
+
+    #[zbus(name = "Disconnect")]
+    fn disconnect(&self) -> zbus::Result<()>;
+
+    #[zbus(name = "GetAppliedConnection")]
+    fn get_applied_connection(&self, flags: u32) -> zbus::Result<(ConnectionSettings, u64)>;
+
+    #[zbus(name = "Reapply")]
+    fn reapply(
+        &self,
+        connection: ConnectionSettings,
+        version_id: u64,
+        flags: u32,
+    ) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device.Statistics",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Statistics {
+    #[zbus(property)]
+    fn refresh_rate_ms(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn set_refresh_rate_ms(&self, value: u32) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn rx_bytes(&self) -> zbus::Result<u64>;
+
+    #[zbus(property)]
+    fn tx_bytes(&self) -> zbus::Result<u64>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.IP4Config",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Ip4Config {
+    #[zbus(property)]
+    fn address_data(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+
+    #[zbus(property)]
+    fn gateway(&self) -> zbus::Result<String>;
+
+    /// DNS servers, each with an "address" entry.
+    #[zbus(property)]
+    fn nameserver_data(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.IP6Config",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Ip6Config {
+    #[zbus(property)]
+    fn address_data(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
 }
 
 #[proxy(
@@ -79,6 +170,19 @@ pub trait Wireless {
     #[zbus(property)]
     fn active_access_point(&self) -> zbus::Result<OwnedObjectPath>;
 
+    /// `NM_WIFI_DEVICE_CAP_*` flags.
+    #[zbus(property)]
+    fn wireless_capabilities(&self) -> zbus::Result<u32>;
+
+    /// Current bit rate in kb/s, 0 when not associated.
+    #[zbus(property)]
+    fn bitrate(&self) -> zbus::Result<u32>;
+
+    /// `CLOCK_BOOTTIME` in milliseconds when the last scan finished, -1 if
+    /// the device never scanned.
+    #[zbus(property)]
+    fn last_scan(&self) -> zbus::Result<i64>;
+
     #[zbus(signal)]
     fn access_point_added(&self, access_point: zbus::zvariant::ObjectPath<'_>) -> zbus::Result<()>;
 
@@ -103,6 +207,10 @@ pub trait AccessPoint {
     #[zbus(property)]
     fn frequency(&self) -> zbus::Result<u32>;
 
+    /// The BSSID.
+    #[zbus(property)]
+    fn hw_address(&self) -> zbus::Result<String>;
+
     #[zbus(property)]
     fn flags(&self) -> zbus::Result<u32>;
 
@@ -118,6 +226,9 @@ pub trait AccessPoint {
     default_service = "org.freedesktop.NetworkManager"
 )]
 pub trait ActiveConnection {
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
     #[zbus(property, name = "Type")]
     fn connection_type(&self) -> zbus::Result<String>;
 
@@ -126,6 +237,15 @@ pub trait ActiveConnection {
 
     #[zbus(property)]
     fn devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    /// The settings profile the connection was activated from.
+    #[zbus(property)]
+    fn connection(&self) -> zbus::Result<OwnedObjectPath>;
+
+    // Named differently from the method zbus generates for the `State`
+    // property's change notifications.
+    #[zbus(signal, name = "StateChanged")]
+    fn activation_state_changed(&self, state: u32, reason: u32) -> zbus::Result<()>;
 }
 
 #[proxy(
@@ -146,6 +266,43 @@ pub trait SettingsConnection {
     #[zbus(name = "GetSettings")]
     fn get_settings(&self) -> zbus::Result<HashMap<String, HashMap<String, OwnedValue>>>;
 
+    #[zbus(name = "GetSecrets")]
+    fn get_secrets(&self, setting_name: &str) -> zbus::Result<ConnectionSettings>;
+
+    #[zbus(name = "Update")]
+    fn update(&self, properties: ConnectionSettings) -> zbus::Result<()>;
+
     #[zbus(name = "Delete")]
     fn delete(&self) -> zbus::Result<()>;
 }
+
+/// Desktop notifications, on the session bus.
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Tray host registry, on the session bus.
+#[proxy(
+    interface = "org.kde.StatusNotifierWatcher",
+    default_service = "org.kde.StatusNotifierWatcher",
+    default_path = "/StatusNotifierWatcher"
+)]
+pub trait StatusNotifierWatcher {
+    fn register_status_notifier_item(&self, service: &str) -> zbus::Result<()>;
+}
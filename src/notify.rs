@@ -0,0 +1,27 @@
+use std::collections::HashMap;
+
+use crate::nm::proxy::NotificationsProxy;
+
+/// Show a desktop notification through the session bus. Does nothing when
+/// there's no session bus or no notification daemon on it.
+pub async fn send(summary: String, body: String) {
+    let Ok(connection) = zbus::Connection::session().await else {
+        return;
+    };
+    let Ok(notifications) = NotificationsProxy::new(&connection).await else {
+        return;
+    };
+    // -1: the daemon's default timeout
+    let _ = notifications
+        .notify(
+            "netman",
+            0,
+            "network-wireless",
+            &summary,
+            &body,
+            &[],
+            HashMap::new(),
+            -1,
+        )
+        .await;
+}
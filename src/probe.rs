@@ -0,0 +1,28 @@
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+use iced::futures::future::{self, Either};
+
+/// Connectivity test target without a `probe_target` setting: Cloudflare's
+/// public DNS resolver.
+pub const DEFAULT_TARGET: &str = "1.1.1.1:53";
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Check that `target` is reachable by opening a TCP connection to it.
+/// Returns the time it took to connect.
+pub async fn tcp_connect(target: String) -> Result<Duration, String> {
+    let addr: SocketAddr = target
+        .parse()
+        .map_err(|e| format!("Invalid address {target}: {e}"))?;
+
+    let start = Instant::now();
+    let connect = std::pin::pin!(async_io::Async::<TcpStream>::connect(addr));
+    let timeout = std::pin::pin!(async_io::Timer::after(TIMEOUT));
+
+    match future::select(connect, timeout).await {
+        Either::Left((Ok(_stream), _)) => Ok(start.elapsed()),
+        Either::Left((Err(e), _)) => Err(format!("{target} unreachable: {e}")),
+        Either::Right(_) => Err(format!("{target} timed out")),
+    }
+}
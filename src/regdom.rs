@@ -0,0 +1,90 @@
+/// The regulatory domain in effect, as `iw reg get` reports it (ISO 3166
+/// country code, "00" for the world domain used until a country is set).
+/// `None` when it can't be read, e.g. without `iw` installed.
+pub fn current() -> Option<String> {
+    let output = std::process::Command::new("iw")
+        .args(["reg", "get"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_iw(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The global domain from `iw reg get` output, its first "country XX:" line.
+fn parse_iw(output: &str) -> Option<String> {
+    let line = output
+        .lines()
+        .find_map(|line| line.strip_prefix("country "))?;
+    let (code, _) = line.split_once(':')?;
+    (code.len() == 2).then(|| code.to_string())
+}
+
+/// Countries following the ETSI rules.
+const ETSI: [&str; 34] = [
+    "AT", "BE", "BG", "CH", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GB", "GR", "HR", "HU",
+    "IE", "IS", "IT", "LI", "LT", "LU", "LV", "MT", "NL", "NO", "PL", "PT", "RO", "SE", "SI", "SK",
+    "TR", "UA",
+];
+
+/// Per-band notes on restricted channels. Broad strokes from the FCC, ETSI and
+/// a few other national rules, not a full regulatory database. "00" is the
+/// world domain.
+pub fn restrictions(regdom: &str) -> [(&'static str, &'static str); 2] {
+    match regdom {
+        "US" | "CA" | "MX" | "BR" => [
+            ("5 GHz", "52-64 and 100-144 need radar detection (DFS)"),
+            ("6 GHz", "all channels (1-233)"),
+        ],
+        c if ETSI.contains(&c) => [
+            (
+                "5 GHz",
+                "52-64 and 100-140 need radar detection (DFS), 144 isn't allowed",
+            ),
+            ("6 GHz", "only channels 1-93"),
+        ],
+        "JP" => [
+            ("5 GHz", "52-64 and 100-144 need radar detection (DFS)"),
+            ("6 GHz", "only channels 1-93"),
+        ],
+        "CN" => [
+            (
+                "5 GHz",
+                "52-64 need radar detection (DFS), 100-144 aren't allowed",
+            ),
+            ("6 GHz", "not allowed"),
+        ],
+        "00" => [
+            (
+                "5 GHz",
+                "no country set, most channels are passive scan only",
+            ),
+            ("6 GHz", "not available until a country is set"),
+        ],
+        _ => [
+            ("5 GHz", "52-144 usually need radar detection (DFS)"),
+            ("6 GHz", "depends on the country"),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iw_output() {
+        let output = "global\ncountry US: DFS-FCC\n\t(902 - 904 @ 2), (N/A, 30), (N/A)\n\n\
+                      phy#0 (self-managed)\ncountry DE: DFS-ETSI\n";
+        assert_eq!(parse_iw(output).as_deref(), Some("US"));
+        assert_eq!(
+            parse_iw("global\ncountry 00: DFS-UNSET\n").as_deref(),
+            Some("00")
+        );
+        assert_eq!(
+            parse_iw("command failed: No such file or directory (-2)\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use iced::widget::canvas;
+use iced::{Color, Element, Point, Rectangle, Renderer, Size, Theme, mouse};
+
+use crate::nm::SharedNetwork;
+
+/// Light modules around the code. Scanners need at least 4.
+const QUIET_ZONE: usize = 4;
+
+/// The `WIFI:` string phones join networks with, e.g.
+/// `WIFI:T:WPA;S:Home;P:secret;;`.
+pub fn payload(network: &SharedNetwork) -> String {
+    let mut payload = match &network.password {
+        Some(password) => {
+            let kind = if network.security == "WEP" {
+                "WEP"
+            } else {
+                "WPA"
+            };
+            format!(
+                "WIFI:T:{kind};S:{};P:{};",
+                escape(&network.ssid),
+                escape(password)
+            )
+        }
+        None => format!("WIFI:T:nopass;S:{};", escape(&network.ssid)),
+    };
+    if network.hidden {
+        payload.push_str("H:true;");
+    }
+    payload.push(';');
+    payload
+}
+
+/// Backslash-escape the characters that separate fields in `WIFI:` strings.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | ';' | ',' | ':') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// A QR code. Drawn black on white whatever the theme, as scanners expect.
+#[derive(Debug)]
+pub struct Code {
+    /// Side length in modules, without the quiet zone.
+    width: usize,
+    /// Row by row.
+    dark: Vec<bool>,
+}
+
+impl Code {
+    pub fn new(data: &str) -> Result<Code, String> {
+        let code =
+            qrcode::QrCode::new(data).map_err(|e| format!("Failed to create the QR code: {e}"))?;
+        Ok(Code {
+            width: code.width(),
+            dark: code
+                .to_colors()
+                .into_iter()
+                .map(|color| color == qrcode::Color::Dark)
+                .collect(),
+        })
+    }
+
+    pub fn view<'a, Message: 'a>(&'a self, size: f32) -> Element<'a, Message> {
+        canvas(self).width(size).height(size).into()
+    }
+}
+
+impl<Message> canvas::Program<Message> for Code {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        frame.fill_rectangle(Point::ORIGIN, bounds.size(), Color::WHITE);
+
+        let module = bounds.width.min(bounds.height) / (self.width + 2 * QUIET_ZONE) as f32;
+        for (i, _) in self.dark.iter().enumerate().filter(|(_, dark)| **dark) {
+            let x = (i % self.width + QUIET_ZONE) as f32;
+            let y = (i / self.width + QUIET_ZONE) as f32;
+            frame.fill_rectangle(
+                Point::new(x * module, y * module),
+                Size::new(module, module),
+                Color::BLACK,
+            );
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_payloads() {
+        let mut network = SharedNetwork {
+            ssid: "Café;Bar".to_string(),
+            security: "WPA2".to_string(),
+            password: Some(r"a:b,c\d".to_string()),
+            hidden: false,
+        };
+        assert_eq!(payload(&network), r"WIFI:T:WPA;S:Café\;Bar;P:a\:b\,c\\d;;");
+
+        network.security = "WEP".to_string();
+        network.password = Some("12345".to_string());
+        network.hidden = true;
+        assert_eq!(
+            payload(&network),
+            r"WIFI:T:WEP;S:Café\;Bar;P:12345;H:true;;"
+        );
+
+        network.security = "Open".to_string();
+        network.password = None;
+        network.hidden = false;
+        assert_eq!(payload(&network), r"WIFI:T:nopass;S:Café\;Bar;;");
+    }
+}
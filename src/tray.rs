@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, Stream, StreamExt};
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Structure, Value};
+
+use crate::nm::proxy::StatusNotifierWatcherProxy;
+
+// The tray icon is a StatusNotifierItem on the session bus, with a dbusmenu
+// menu.
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+
+/// The tray's connection, for `update`.
+static CONNECTION: std::sync::Mutex<Option<zbus::Connection>> = std::sync::Mutex::new(None);
+
+/// What the tray icon shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    /// Icon theme name.
+    icon: &'static str,
+    /// Tooltip, e.g. "HomeNetwork · 72%".
+    text: String,
+    wifi_enabled: bool,
+    connected: bool,
+}
+
+impl Status {
+    pub fn connected(ssid: &str, strength: u8) -> Self {
+        let icon = match crate::signal_bars(strength) {
+            1 => "network-wireless-signal-weak-symbolic",
+            2 => "network-wireless-signal-ok-symbolic",
+            3 => "network-wireless-signal-good-symbolic",
+            _ => "network-wireless-signal-excellent-symbolic",
+        };
+        Status {
+            icon,
+            text: format!("{ssid}  ·  {strength}%"),
+            wifi_enabled: true,
+            connected: true,
+        }
+    }
+
+    pub fn connecting(ssid: &str) -> Self {
+        Status {
+            icon: "network-wireless-acquiring-symbolic",
+            text: format!("Connecting to {ssid}"),
+            wifi_enabled: true,
+            connected: false,
+        }
+    }
+
+    pub fn disconnected() -> Self {
+        Status {
+            icon: "network-wireless-offline-symbolic",
+            text: "Disconnected".to_string(),
+            wifi_enabled: true,
+            connected: false,
+        }
+    }
+
+    pub fn wifi_off() -> Self {
+        Status {
+            icon: "network-wireless-disabled-symbolic",
+            text: "WiFi off".to_string(),
+            wifi_enabled: false,
+            connected: false,
+        }
+    }
+}
+
+/// A tray menu entry or icon click.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Open,
+    ToggleWifi,
+    Disconnect,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Whether the icon could be registered with the tray.
+    Ready(bool),
+    Action(Action),
+}
+
+/// Shows the tray icon for as long as the stream is polled.
+pub fn run() -> impl Stream<Item = Event> {
+    iced::stream::channel(10, async |mut output: mpsc::Sender<Event>| {
+        let (actions, mut received) = mpsc::unbounded();
+        match start(actions).await {
+            Ok(connection) => {
+                *CONNECTION.lock().unwrap_or_else(|e| e.into_inner()) = Some(connection);
+                let _ = output.send(Event::Ready(true)).await;
+            }
+            Err(e) => {
+                eprintln!("netman: can't show the tray icon: {e}");
+                let _ = output.send(Event::Ready(false)).await;
+                return;
+            }
+        }
+        while let Some(action) = received.next().await {
+            let _ = output.send(Event::Action(action)).await;
+        }
+    })
+}
+
+async fn start(actions: mpsc::UnboundedSender<Action>) -> zbus::Result<zbus::Connection> {
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    let status = Status::disconnected();
+    let connection = zbus::connection::Builder::session()?
+        .name(name.as_str())?
+        .serve_at(
+            ITEM_PATH,
+            Item {
+                status: status.clone(),
+                actions: actions.clone(),
+            },
+        )?
+        .serve_at(
+            MENU_PATH,
+            Menu {
+                status,
+                revision: 0,
+                actions,
+            },
+        )?
+        .build()
+        .await?;
+    StatusNotifierWatcherProxy::new(&connection)
+        .await?
+        .register_status_notifier_item(&name)
+        .await?;
+    Ok(connection)
+}
+
+/// Show `status` in the tray icon and its menu.
+pub async fn update(status: Status) {
+    let Some(connection) = CONNECTION.lock().unwrap_or_else(|e| e.into_inner()).clone() else {
+        return;
+    };
+    let server = connection.object_server();
+
+    if let Ok(item) = server.interface::<_, Item>(ITEM_PATH).await {
+        item.get_mut().await.status = status.clone();
+        let emitter = item.signal_emitter();
+        let _ = Item::new_icon(emitter).await;
+        let _ = Item::new_title(emitter).await;
+        let _ = Item::new_tool_tip(emitter).await;
+    }
+
+    if let Ok(menu) = server.interface::<_, Menu>(MENU_PATH).await {
+        let revision = {
+            let mut menu = menu.get_mut().await;
+            menu.status = status;
+            menu.revision += 1;
+            menu.revision
+        };
+        let _ = Menu::layout_updated(menu.signal_emitter(), revision, 0).await;
+    }
+}
+
+/// (width, height, ARGB32 data)
+type Pixmap = (i32, i32, Vec<u8>);
+
+/// (icon name, icon pixmaps, title, description)
+type ToolTip = (String, Vec<Pixmap>, String, String);
+
+struct Item {
+    status: Status,
+    actions: mpsc::UnboundedSender<Action>,
+}
+
+#[zbus::interface(name = "org.kde.StatusNotifierItem")]
+impl Item {
+    #[zbus(property)]
+    fn category(&self) -> &str {
+        "Hardware"
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> &str {
+        "netman"
+    }
+
+    #[zbus(property)]
+    fn title(&self) -> String {
+        format!("netman: {}", self.status.text)
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[zbus(property)]
+    fn icon_name(&self) -> &str {
+        self.status.icon
+    }
+
+    #[zbus(property)]
+    fn tool_tip(&self) -> ToolTip {
+        (
+            self.status.icon.to_string(),
+            Vec::new(),
+            "netman".to_string(),
+            self.status.text.clone(),
+        )
+    }
+
+    #[zbus(property)]
+    fn item_is_menu(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn menu(&self) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(MENU_PATH).expect("valid object path")
+    }
+
+    fn activate(&self, _x: i32, _y: i32) {
+        let _ = self.actions.unbounded_send(Action::Open);
+    }
+
+    fn secondary_activate(&self, _x: i32, _y: i32) {}
+
+    fn context_menu(&self, _x: i32, _y: i32) {}
+
+    fn scroll(&self, _delta: i32, _orientation: &str) {}
+
+    #[zbus(signal)]
+    async fn new_title(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn new_icon(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn new_tool_tip(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+}
+
+/// Menu entry properties: property name -> value.
+type Properties = HashMap<String, Value<'static>>;
+
+/// A dbusmenu layout node: (id, properties, children).
+type Layout = (i32, Properties, Vec<Value<'static>>);
+
+struct Menu {
+    status: Status,
+    /// Bumped on every change, hosts refetch the layout when it changes.
+    revision: u32,
+    actions: mpsc::UnboundedSender<Action>,
+}
+
+impl Menu {
+    /// The entries, by id. 0 is the root.
+    fn entries(&self) -> Vec<(i32, Properties)> {
+        let entry = |label: &str, enabled: bool| {
+            HashMap::from([
+                ("label".to_string(), Value::from(label.to_string())),
+                ("enabled".to_string(), Value::from(enabled)),
+            ])
+        };
+        let wifi = if self.status.wifi_enabled {
+            "Turn WiFi off"
+        } else {
+            "Turn WiFi on"
+        };
+        vec![
+            (
+                0,
+                HashMap::from([(
+                    "children-display".to_string(),
+                    Value::from("submenu".to_string()),
+                )]),
+            ),
+            (1, entry("Open netman", true)),
+            (2, entry(wifi, true)),
+            (3, entry("Disconnect", self.status.connected)),
+        ]
+    }
+
+    fn layout(&self, id: i32) -> Option<Layout> {
+        let mut entries = self.entries();
+        if id != 0 {
+            let (id, properties) = entries.into_iter().find(|(i, _)| *i == id)?;
+            return Some((id, properties, Vec::new()));
+        }
+        let (_, root) = entries.remove(0);
+        let children = entries
+            .into_iter()
+            .map(|(id, properties)| {
+                Value::from(Structure::from((id, properties, Vec::<Value>::new())))
+            })
+            .collect();
+        Some((0, root, children))
+    }
+
+    fn clicked(&self, id: i32) -> bool {
+        let action = match id {
+            1 => Action::Open,
+            2 => Action::ToggleWifi,
+            3 => Action::Disconnect,
+            _ => return false,
+        };
+        let _ = self.actions.unbounded_send(action);
+        true
+    }
+}
+
+#[zbus::interface(name = "com.canonical.dbusmenu")]
+impl Menu {
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        _recursion_depth: i32,
+        _property_names: Vec<String>,
+    ) -> zbus::fdo::Result<(u32, Layout)> {
+        let layout = self
+            .layout(parent_id)
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("No menu entry {parent_id}")))?;
+        Ok((self.revision, layout))
+    }
+
+    fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        _property_names: Vec<String>,
+    ) -> Vec<(i32, Properties)> {
+        self.entries()
+            .into_iter()
+            .filter(|(id, _)| ids.is_empty() || ids.contains(id))
+            .collect()
+    }
+
+    fn get_property(&self, id: i32, name: &str) -> zbus::fdo::Result<Value<'static>> {
+        self.entries()
+            .into_iter()
+            .find(|(i, _)| *i == id)
+            .and_then(|(_, mut properties)| properties.remove(name))
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("No property {name} on {id}")))
+    }
+
+    fn event(&self, id: i32, event_id: &str, _data: OwnedValue, _timestamp: u32) {
+        if event_id == "clicked" {
+            self.clicked(id);
+        }
+    }
+
+    /// Returns the ids that don't exist.
+    fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+        events
+            .into_iter()
+            .filter(|(id, event_id, _, _)| event_id == "clicked" && !self.clicked(*id))
+            .map(|(id, _, _, _)| id)
+            .collect()
+    }
+
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    /// Returns (ids needing an update, ids that don't exist).
+    fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        (Vec::new(), Vec::new())
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[zbus(property)]
+    fn text_direction(&self) -> &str {
+        "ltr"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(signal)]
+    async fn layout_updated(
+        emitter: &SignalEmitter<'_>,
+        revision: u32,
+        parent: i32,
+    ) -> zbus::Result<()>;
+}